@@ -0,0 +1,340 @@
+// Properties of a trace that do not depend on a cache, apart from the line size.
+//
+// The reuse distance of an access is the number of other lines that were accessed since the last
+// access to its line. A fully associative LRU cache with n lines hits exactly the accesses with a
+// distance below n, so the histogram of distances gives the hit rate of every such cache at once.
+// Distances are counted with a Fenwick tree that marks the time of the last access to every line.
+// It is compacted when it runs full, so memory grows with the number of lines, not of accesses.
+//
+// Version 1 of the JSON output:
+//
+//     {
+//       "version": 1,
+//       "line_size": 64, "records": 5, "loads": 4, "stores": 1, "modifies": 0, "instructions": 0,
+//       "accesses": 5, "lines": 3, "footprint": 192, "lowest_address": 4096, "highest_address": 4240,
+//       "cold": 3,
+//       "reuse": [ { "min": 0, "max": 0, "accesses": 1, "lru_lines": 1, "lru_hit_rate": 0.2 } ]
+//     }
+//
+// `accesses` counts the lines touched by the records like the simulation does, e.g. twice for a
+// modify. `cold` accesses are the first ones to their line. The `reuse` buckets hold the accesses
+// with a distance from `min` to `max`: 0, 1, 2 to 3, 4 to 7 and so on up to the largest distance.
+// `lru_hit_rate` is the share of all accesses that hit in a fully associative LRU cache with
+// `lru_lines` lines, i.e. of the accesses in this and all previous buckets.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, Write};
+
+use crate::sim;
+use crate::trace::{AccessKind, Trace};
+
+/// The newest version of the JSON output.
+pub const VERSION: u32 = 1;
+
+/// Reuse distances of a sequence of line accesses.
+struct ReuseDistances {
+    /// The slot of the last access to every line.
+    last: HashMap<u64, usize>,
+    /// Fenwick tree over the slots, 1-based. A slot counts 1 if it is the last access to a line.
+    tree: Vec<i64>,
+    next: usize,
+}
+
+impl ReuseDistances {
+    fn new() -> Self {
+        ReuseDistances { last: HashMap::new(), tree: vec![0; 1025], next: 0 }
+    }
+
+    fn add(&mut self, slot: usize, delta: i64) {
+        let mut i = slot + 1;
+        while i < self.tree.len() {
+            self.tree[i] += delta;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    /// Number of marked slots before `slot`.
+    fn prefix(&self, slot: usize) -> i64 {
+        let mut sum = 0;
+        let mut i = slot;
+        while i > 0 {
+            sum += self.tree[i];
+            i -= i & i.wrapping_neg();
+        }
+        sum
+    }
+
+    /// Numbers the last accesses from 0 again, in their order, and makes room for as many more.
+    fn compact(&mut self) {
+        let mut lines: Vec<(usize, u64)> = self.last.iter().map(|(&line, &slot)| (slot, line)).collect();
+        lines.sort_unstable();
+
+        self.tree = vec![0; (2 * lines.len()).max(1024) + 1];
+        self.next = lines.len();
+        for (slot, (_, line)) in lines.into_iter().enumerate() {
+            self.last.insert(line, slot);
+            self.add(slot, 1);
+        }
+    }
+
+    /// Records an access to `line` and returns its reuse distance, `None` for the first access.
+    fn access(&mut self, line: u64) -> Option<u64> {
+        if self.next + 1 == self.tree.len() {
+            self.compact();
+        }
+
+        let distance = self.last.get(&line).copied().map(|slot| {
+            let distance = self.prefix(self.next) - self.prefix(slot + 1);
+            self.add(slot, -1);
+            distance as u64
+        });
+        self.add(self.next, 1);
+        self.last.insert(line, self.next);
+        self.next += 1;
+        distance
+    }
+}
+
+/// Index of the histogram bucket of `distance`: 0, 1, 2 to 3, 4 to 7 and so on.
+fn bucket(distance: u64) -> usize {
+    (u64::BITS - distance.leading_zeros()) as usize
+}
+
+/// The smallest and largest distance in `bucket`.
+fn bucket_range(bucket: usize) -> (u64, u64) {
+    match bucket {
+        0 => (0, 0),
+        _ => (1 << (bucket - 1), u64::MAX >> (u64::BITS as usize - bucket)),
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub line_size: u64,
+    pub records: u64,
+    pub loads: u64,
+    pub stores: u64,
+    pub modifies: u64,
+    pub instructions: u64,
+    pub accesses: u64,
+    /// Number of distinct lines that were accessed.
+    pub lines: u64,
+    pub lowest_address: Option<u64>,
+    pub highest_address: Option<u64>,
+    pub cold: u64,
+    /// Number of accesses in every bucket of reuse distances, see `bucket`.
+    pub reuse: Vec<u64>,
+}
+
+impl Analysis {
+    /// Reads the whole trace, grouping addresses into lines of `offset_bits`.
+    pub fn run(trace: Trace, offset_bits: u64) -> Result<Analysis, Box<dyn Error>> {
+        let mut analysis = Analysis { line_size: 1 << offset_bits, ..Analysis::default() };
+        let mut distances = ReuseDistances::new();
+
+        for access in trace.accesses {
+            let access = access?;
+            analysis.records += 1;
+            match access.kind {
+                AccessKind::Load => analysis.loads += 1,
+                AccessKind::Store => analysis.stores += 1,
+                AccessKind::Modify => analysis.modifies += 1,
+                AccessKind::Instruction => analysis.instructions += 1,
+            }
+            let last_byte = access.addr.saturating_add(access.size.max(1) - 1);
+            analysis.lowest_address = Some(analysis.lowest_address.map_or(access.addr, |lowest| lowest.min(access.addr)));
+            analysis.highest_address = Some(analysis.highest_address.map_or(last_byte, |highest| highest.max(last_byte)));
+
+            for addr in access.line_addrs(offset_bits) {
+                analysis.accesses += 1;
+                match distances.access(addr >> offset_bits) {
+                    Some(distance) => {
+                        let bucket = bucket(distance);
+                        if analysis.reuse.len() <= bucket {
+                            analysis.reuse.resize(bucket + 1, 0);
+                        }
+                        analysis.reuse[bucket] += 1;
+                    }
+                    None => analysis.cold += 1,
+                }
+            }
+        }
+        analysis.lines = distances.last.len() as u64;
+
+        Ok(analysis)
+    }
+
+    /// The buckets with their range of distances, the number of lines of the LRU cache that hits
+    /// them and all buckets before, and its hit rate.
+    fn buckets(&self) -> impl Iterator<Item = ((u64, u64), u64, u64, f64)> + '_ {
+        let mut hits = 0;
+        self.reuse.iter().enumerate().map(move |(bucket, &accesses)| {
+            hits += accesses;
+            let (min, max) = bucket_range(bucket);
+            ((min, max), accesses, max + 1, hits as f64 / self.accesses as f64)
+        })
+    }
+
+    pub fn write_text(&self, writer: &mut impl Write) -> io::Result<()> {
+        writeln!(
+            writer,
+            "Records: {} ({} loads, {} stores, {} modifies, {} instructions)",
+            self.records, self.loads, self.stores, self.modifies, self.instructions
+        )?;
+        writeln!(writer, "Accesses: {} to lines of {}", self.accesses, sim::format_size(self.line_size))?;
+        writeln!(
+            writer,
+            "Lines: {} ({} footprint)",
+            self.lines,
+            sim::format_size(self.lines * self.line_size)
+        )?;
+        if let (Some(lowest), Some(highest)) = (self.lowest_address, self.highest_address) {
+            writeln!(writer, "Addresses: {:x} to {:x}", lowest, highest)?;
+        }
+        writeln!(writer, "Cold accesses: {}", self.cold)?;
+        if self.reuse.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(writer)?;
+        let rows: Vec<[String; 4]> = self
+            .buckets()
+            .map(|((min, max), accesses, lru_lines, hit_rate)| {
+                [
+                    if min == max { min.to_string() } else { format!("{}-{}", min, max) },
+                    accesses.to_string(),
+                    format!("{} ({})", lru_lines, sim::format_size(lru_lines * self.line_size)),
+                    format!("{:.1}%", hit_rate * 100.0),
+                ]
+            })
+            .collect();
+        let header = ["Reuse distance", "Accesses", "LRU lines", "LRU hit rate"];
+        let widths: Vec<usize> = (0..header.len())
+            .map(|i| rows.iter().map(|row| row[i].len()).fold(header[i].len(), usize::max))
+            .collect();
+        writeln!(
+            writer,
+            "{:<w0$}  {:>w1$}  {:>w2$}  {:>w3$}",
+            header[0], header[1], header[2], header[3],
+            w0 = widths[0], w1 = widths[1], w2 = widths[2], w3 = widths[3],
+        )?;
+        for row in rows {
+            writeln!(
+                writer,
+                "{:<w0$}  {:>w1$}  {:>w2$}  {:>w3$}",
+                row[0], row[1], row[2], row[3],
+                w0 = widths[0], w1 = widths[1], w2 = widths[2], w3 = widths[3],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_json(&self, writer: &mut impl Write) -> io::Result<()> {
+        let reuse: Vec<Bucket> = self
+            .buckets()
+            .map(|((min, max), accesses, lru_lines, lru_hit_rate)| Bucket { min, max, accesses, lru_lines, lru_hit_rate })
+            .collect();
+        let document = Document {
+            version: VERSION,
+            line_size: self.line_size,
+            records: self.records,
+            loads: self.loads,
+            stores: self.stores,
+            modifies: self.modifies,
+            instructions: self.instructions,
+            accesses: self.accesses,
+            lines: self.lines,
+            footprint: self.lines * self.line_size,
+            lowest_address: self.lowest_address,
+            highest_address: self.highest_address,
+            cold: self.cold,
+            reuse,
+        };
+        serde_json::to_writer(&mut *writer, &document)?;
+        writeln!(writer)
+    }
+}
+
+#[derive(serde::Serialize)]
+struct Document {
+    version: u32,
+    line_size: u64,
+    records: u64,
+    loads: u64,
+    stores: u64,
+    modifies: u64,
+    instructions: u64,
+    accesses: u64,
+    lines: u64,
+    footprint: u64,
+    lowest_address: Option<u64>,
+    highest_address: Option<u64>,
+    cold: u64,
+    reuse: Vec<Bucket>,
+}
+
+#[derive(serde::Serialize)]
+struct Bucket {
+    min: u64,
+    max: u64,
+    accesses: u64,
+    lru_lines: u64,
+    lru_hit_rate: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::generate::Rng;
+
+    use super::*;
+
+    /// The reuse distance of every access, counted directly.
+    fn naive_distances(lines: &[u64]) -> Vec<Option<u64>> {
+        (0..lines.len())
+            .map(|i| {
+                let last = lines[..i].iter().rposition(|&line| line == lines[i])?;
+                Some(lines[last + 1..i].iter().collect::<HashSet<_>>().len() as u64)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_naive_distances() {
+        // Long enough to compact the tree several times, with more lines than its initial half.
+        let mut rng = Rng::new(7);
+        let lines: Vec<u64> = (0..5000)
+            .map(|i| if i % 3 == 0 { rng.below(700) } else { rng.below(16) })
+            .collect();
+
+        let mut distances = ReuseDistances::new();
+        let counted: Vec<_> = lines.iter().map(|&line| distances.access(line)).collect();
+        assert!(distances.tree.len() > 1025);
+        assert_eq!(counted, naive_distances(&lines));
+    }
+
+    #[test]
+    fn repeated_and_alternating_lines() {
+        let mut distances = ReuseDistances::new();
+        let counted: Vec<_> = [1, 1, 2, 1, 3, 2, 1].into_iter().map(|line| distances.access(line)).collect();
+        assert_eq!(counted, [None, Some(0), None, Some(1), None, Some(2), Some(2)]);
+    }
+
+    #[test]
+    fn buckets() {
+        let buckets: Vec<_> = [0, 1, 2, 3, 4, 7, 8, u64::MAX].into_iter().map(bucket).collect();
+        assert_eq!(buckets, [0, 1, 2, 2, 3, 3, 4, 64]);
+
+        assert_eq!(bucket_range(0), (0, 0));
+        assert_eq!(bucket_range(1), (1, 1));
+        assert_eq!(bucket_range(3), (4, 7));
+        assert_eq!(bucket_range(64), (1 << 63, u64::MAX));
+        for distance in [0, 1, 5, 1000, u64::MAX] {
+            let (min, max) = bucket_range(bucket(distance));
+            assert!((min..=max).contains(&distance));
+        }
+    }
+}
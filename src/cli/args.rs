@@ -0,0 +1,241 @@
+// The arguments of a command. Options are taken out by name, as `--option value` or
+// `--option=value`, in any order and anywhere before `--`. What is left are the positional
+// arguments, which must not look like options, except for `-`, which stands for stdin.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Why the command line does not make sense. Reported with exit status `EXIT_USAGE`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum InvalidArgumentsError {
+    UnknownCommand(String),
+    UnknownOption(String),
+    MissingValue { option: String },
+    InvalidValue { option: String, value: String, reason: String },
+    MissingArgument { name: &'static str },
+    UnexpectedArgument(String),
+    /// Options that cannot be used together or only together with others.
+    Conflict(&'static str),
+    /// Values that are valid on their own, but describe nothing together, e.g. empty bounds.
+    Unsatisfiable(String),
+}
+
+impl fmt::Display for InvalidArgumentsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidArgumentsError::UnknownCommand(command) => write!(f, "Unknown command \"{}\"", command),
+            InvalidArgumentsError::UnknownOption(option) => write!(f, "Unknown option \"{}\"", option),
+            InvalidArgumentsError::MissingValue { option } => write!(f, "Missing value for {}", option),
+            InvalidArgumentsError::InvalidValue { option, value, reason } => {
+                write!(f, "Invalid value \"{}\" for {}: {}", value, option, reason)
+            }
+            InvalidArgumentsError::MissingArgument { name } => write!(f, "Missing argument {}", name),
+            InvalidArgumentsError::UnexpectedArgument(argument) => write!(f, "Unexpected argument \"{}\"", argument),
+            InvalidArgumentsError::Conflict(message) => write!(f, "{}", message),
+            InvalidArgumentsError::Unsatisfiable(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for InvalidArgumentsError {}
+
+pub struct Args {
+    args: Vec<String>,
+}
+
+impl Args {
+    /// `args` are the arguments after the command.
+    pub fn new(args: Vec<String>) -> Self {
+        Args { args }
+    }
+
+    /// Number of arguments before `--`, the only ones that can be options.
+    fn options_end(&self) -> usize {
+        self.args.iter().position(|arg| arg == "--").unwrap_or(self.args.len())
+    }
+
+    /// Removes every occurrence of `flag` and returns whether there was one.
+    pub fn flag(&mut self, flag: &str) -> bool {
+        let end = self.options_end();
+        let len = self.args.len();
+        let mut i = 0;
+        self.args.retain(|arg| {
+            i += 1;
+            i > end || arg != flag
+        });
+        self.args.len() != len
+    }
+
+    /// Removes the last occurrence of `option` with its value and returns the value.
+    pub fn option(&mut self, option: &str) -> Result<Option<String>, InvalidArgumentsError> {
+        let end = self.options_end();
+        let Some(position) = self.args[..end].iter().rposition(|arg| {
+            arg == option || arg.strip_prefix(option).is_some_and(|rest| rest.starts_with('='))
+        }) else {
+            return Ok(None);
+        };
+
+        let arg = self.args.remove(position);
+        if let Some((_, value)) = arg.split_once('=') {
+            return Ok(Some(value.to_string()));
+        }
+        // The value has to come before `--`.
+        if position + 1 >= end {
+            return Err(InvalidArgumentsError::MissingValue { option: option.to_string() });
+        }
+        Ok(Some(self.args.remove(position)))
+    }
+
+    /// Removes `option` and parses its value with `parse`.
+    pub fn parse_with<T, E: fmt::Display>(
+        &mut self,
+        option: &str,
+        parse: impl Fn(&str) -> Result<T, E>,
+    ) -> Result<Option<T>, InvalidArgumentsError> {
+        self.option(option)?
+            .map(|value| {
+                parse(&value).map_err(|err| InvalidArgumentsError::InvalidValue {
+                    option: option.to_string(),
+                    value: value.clone(),
+                    reason: err.to_string(),
+                })
+            })
+            .transpose()
+    }
+
+    pub fn parse<T: FromStr>(&mut self, option: &str) -> Result<Option<T>, InvalidArgumentsError>
+    where
+        T::Err: fmt::Display,
+    {
+        self.parse_with(option, str::parse)
+    }
+
+    /// Removes `option` and parses its value as a comma separated list.
+    pub fn list_with<T, E: fmt::Display>(
+        &mut self,
+        option: &str,
+        parse: impl Fn(&str) -> Result<T, E>,
+    ) -> Result<Option<Vec<T>>, InvalidArgumentsError> {
+        self.parse_with(option, |list| list.split(',').map(|item| parse(item.trim())).collect())
+    }
+
+    /// Returns the positional arguments once all options are taken. There have to be at least
+    /// as many as `names` has entries, they are reported as missing otherwise, and at most `max`.
+    pub fn finish(self, names: &[&'static str], max: usize) -> Result<Vec<String>, InvalidArgumentsError> {
+        let end = self.options_end();
+        let mut positional = Vec::with_capacity(self.args.len());
+        for (i, arg) in self.args.into_iter().enumerate() {
+            if i < end && arg.starts_with('-') && arg != "-" {
+                return Err(InvalidArgumentsError::UnknownOption(arg));
+            }
+            if i != end {
+                positional.push(arg);
+            }
+        }
+
+        if let Some(name) = names.get(positional.len()) {
+            return Err(InvalidArgumentsError::MissingArgument { name });
+        }
+        if let Some(argument) = positional.get(max) {
+            return Err(InvalidArgumentsError::UnexpectedArgument(argument.clone()));
+        }
+        Ok(positional)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Args {
+        Args::new(args.iter().map(|arg| arg.to_string()).collect())
+    }
+
+    #[test]
+    fn options_with_values() {
+        let mut args = args(&["--format=lackey", "trace", "--config", "a.toml", "--config", "b.toml", "--cache="]);
+        assert_eq!(args.option("--format"), Ok(Some("lackey".to_string())));
+        // The last occurrence wins, the others are left over.
+        assert_eq!(args.option("--config"), Ok(Some("b.toml".to_string())));
+        assert_eq!(args.option("--cache"), Ok(Some(String::new())));
+        assert_eq!(args.option("--assoc"), Ok(None));
+        assert_eq!(
+            args.finish(&["TRACE"], 1),
+            Err(InvalidArgumentsError::UnknownOption("--config".to_string()))
+        );
+    }
+
+    #[test]
+    fn option_prefixes() {
+        let mut args = args(&["--block-size-x", "4", "--block", "4"]);
+        assert_eq!(args.option("--block-size"), Ok(None));
+        assert_eq!(args.option("--block"), Ok(Some("4".to_string())));
+    }
+
+    #[test]
+    fn missing_values() {
+        assert_eq!(
+            args(&["trace", "--config"]).option("--config"),
+            Err(InvalidArgumentsError::MissingValue { option: "--config".to_string() })
+        );
+        // Values have to come before `--`.
+        assert_eq!(
+            args(&["--config", "--", "a.toml"]).option("--config"),
+            Err(InvalidArgumentsError::MissingValue { option: "--config".to_string() })
+        );
+        assert_eq!(args(&["--config", "-"]).option("--config"), Ok(Some("-".to_string())));
+    }
+
+    #[test]
+    fn double_dash() {
+        let mut args = args(&["--strict", "--", "--strict", "--config", "-"]);
+        assert!(args.flag("--strict"));
+        assert!(!args.flag("--strict"));
+        assert_eq!(args.option("--config"), Ok(None));
+        // Everything after `--` is positional, `--` itself is dropped.
+        assert_eq!(
+            args.finish(&["TRACE"], 3),
+            Ok(vec!["--strict".to_string(), "--config".to_string(), "-".to_string()])
+        );
+    }
+
+    #[test]
+    fn flags() {
+        let mut args = args(&["--strict", "trace", "--strict"]);
+        assert!(args.flag("--strict"));
+        assert!(!args.flag("--watch"));
+        assert_eq!(args.finish(&["TRACE"], 1), Ok(vec!["trace".to_string()]));
+    }
+
+    #[test]
+    fn positional_arguments() {
+        assert_eq!(args(&["-"]).finish(&["TRACE"], 1), Ok(vec!["-".to_string()]));
+        assert_eq!(
+            args(&["trace"]).finish(&["TRACE", "OUTPUT"], 2),
+            Err(InvalidArgumentsError::MissingArgument { name: "OUTPUT" })
+        );
+        assert_eq!(
+            args(&["a", "b"]).finish(&["TRACE"], 1),
+            Err(InvalidArgumentsError::UnexpectedArgument("b".to_string()))
+        );
+        assert_eq!(
+            args(&["-x", "trace"]).finish(&["TRACE"], 1),
+            Err(InvalidArgumentsError::UnknownOption("-x".to_string()))
+        );
+    }
+
+    #[test]
+    fn parsed_values() {
+        let mut args = args(&["--assoc", "two", "--block-size", "1, 2,4"]);
+        assert_eq!(
+            args.parse::<u64>("--assoc"),
+            Err(InvalidArgumentsError::InvalidValue {
+                option: "--assoc".to_string(),
+                value: "two".to_string(),
+                reason: "invalid digit found in string".to_string(),
+            })
+        );
+        assert_eq!(args.list_with("--block-size", str::parse::<u64>), Ok(Some(vec![1, 2, 4])));
+    }
+}
@@ -0,0 +1,677 @@
+// The command line. The first argument names the command, without one the window is opened,
+// optionally with a trace. Every command has its own options, see `help`, and reports problems on
+// stderr with a non-zero exit status instead of panicking:
+//
+// - 0: Success.
+// - 1 (`EXIT_FAILURE`): The command failed, e.g. because a trace or configuration is malformed.
+// - 2 (`EXIT_USAGE`): The command line is invalid, e.g. an unknown option or a missing argument.
+//
+// The older forms `--no-window TRACE` and `--convert INPUT OUTPUT` are still accepted for
+// `simulate` and `convert`.
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::analyze::Analysis;
+use crate::config::Config;
+use crate::exercise::{Bounds, Exercise, ExerciseError};
+use crate::export::{self, Blank, OutputFormat};
+use crate::generate::{self, Workload};
+use crate::grade::{self, AnswerError, Answers};
+use crate::repl::Repl;
+use crate::sim::{self, CacheDesc, CacheOverrides, LevelResult, Strategy};
+use crate::sweep::{self, Sweep};
+use crate::trace;
+use crate::{open_trace, run_sim, SimOptions};
+
+mod args;
+
+pub use args::{Args, InvalidArgumentsError};
+
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+
+/// What `main` does once the command line is handled.
+pub enum Outcome {
+    /// Open the window with these arguments, the program name first.
+    Gui(Vec<String>),
+    Exit(i32),
+}
+
+const COMMANDS: [&str; 10] = ["gui", "simulate", "sweep", "analyze", "repl", "convert", "generate", "exercise", "grade", "help"];
+
+const HELP: &str = "\
+Usage: cachecache [COMMAND] [OPTIONS] [ARGUMENTS]
+
+Simulates caches on traces of memory accesses.
+
+Commands:
+  gui       Open the window, optionally with a trace (the default)
+  simulate  Simulate a trace and print the results
+  sweep     Simulate a trace with several caches and compare them
+  analyze   Print properties of a trace that do not depend on the cache
+  repl      Type accesses to a cache one at a time and see what they do
+  convert   Convert a trace to the compact binary format
+  generate  Write a synthetic trace of a common access pattern
+  exercise  Print a randomized exercise or its solution
+  grade     Grade answer files against the simulation of a trace
+  help      Print the help of a command
+
+Run \"cachecache help COMMAND\" for the options of a command.
+
+Exit status: 0 on success, 1 if the command failed, 2 if the command line is invalid.
+";
+
+const TRACE_OPTIONS: &str = "
+Trace options:
+  --format FORMAT       Trace format: native, lackey, dinero, binary or kernel, detected by default
+  --strict              Reject malformed lines instead of skipping them
+  --config FILE         Take the cache levels from a TOML or JSON configuration
+  --cache SPEC          Take the cache from a description like \"32KiB, 64B line, 8-way, LRU\"
+";
+
+const OVERRIDE_OPTIONS: &str = "
+Cache overrides, applied to the cache of the trace, --cache or the first level of --config:
+  --address-size BITS   Address size in bits
+  --block-size BITS     Block size in bits, i.e. the number of offset bits
+  --block-count N       Number of blocks
+  --assoc N             Associativity
+  --strategy NAME       Replacement strategy: LRU, LFU or First
+";
+
+/// The help of `command`, or `None` if there is no such command.
+fn help(command: &str) -> Option<String> {
+    let (usage, trace_options, overrides) = match command {
+        "gui" => (
+            "Usage: cachecache [gui] [OPTIONS] [TRACE]
+
+Opens the window. A trace given on the command line is selected but not simulated yet.
+",
+            true,
+            true,
+        ),
+        "simulate" => (
+            "Usage: cachecache simulate [OPTIONS] TRACE
+
+Simulates the trace and prints the lines of the cache and the statistics. TRACE may be - for stdin.
+
+Options:
+  --output-format FORMAT  text, json, csv, latex or markdown (default: text)
+  --events              Print a table of every access before the lines, only as text
+  --report FILE         Also write a self-contained HTML report
+  --blank LIST          Leave the cells of these columns empty in latex and markdown tables:
+                        tag, set, offset, result, way, evicted, lines, stats or all
+  --no-history          Only keep the final content of every line, in constant memory
+  --watch               Simulate again and print the results whenever the trace or configuration
+                        changes, until interrupted
+",
+            true,
+            true,
+        ),
+        "sweep" => (
+            "Usage: cachecache sweep [OPTIONS] TRACE
+
+Simulates the trace with every combination of the given values and prints the statistics of each
+cache. The values replace the fields of the cache of the trace, --cache or the first level of
+--config, lower levels are ignored. Combinations that do not describe a cache are skipped.
+
+Options:
+  --address-size LIST   Address sizes in bits, e.g. 32,48
+  --block-size LIST     Block sizes in bits, e.g. 4,5,6
+  --block-count LIST    Numbers of blocks
+  --assoc LIST          Associativities, e.g. 1,2,4,8
+  --strategy LIST       Replacement strategies, e.g. LRU,LFU
+  --output-format FORMAT  text, json or csv (default: text)
+",
+            true,
+            false,
+        ),
+        "analyze" => (
+            "Usage: cachecache analyze [OPTIONS] TRACE
+
+Prints the number of accesses and lines of the trace, its footprint and the histogram of reuse
+distances, which gives the hit rate of fully associative LRU caches of every size. Lines have the
+block size of the cache of the trace, --cache or --config if there is one, 64 bytes otherwise.
+
+Options:
+  --block-size BITS     Block size in bits
+  --output-format FORMAT  text or json (default: text)
+",
+            true,
+            false,
+        ),
+        "repl" => (
+            "Usage: cachecache repl [OPTIONS] [TRACE]
+
+Reads addresses, ranges and patterns from stdin and prints for every access whether it hits, its
+set and way and the content of the set. Type \"help\" in the session for the commands, e.g. to
+undo inputs or save the accesses as a trace. With TRACE, its cache is used and its accesses are
+simulated first. Lower levels of a configuration are ignored. When stdin is not a terminal, the
+session stops at the first invalid line.
+",
+            true,
+            true,
+        ),
+        "convert" => (
+            "Usage: cachecache convert [--format FORMAT] [--strict] INPUT OUTPUT
+
+Converts a trace of any format to the compact binary format, keeping the cache it describes.
+",
+            false,
+            false,
+        ),
+        "generate" => (
+            "Usage: cachecache generate [OPTIONS] PATTERN [OUTPUT]
+
+Writes a synthetic trace to OUTPUT or stdout. The patterns are sequential, strided, random, zipf,
+row-major, column-major, matmul and pointer-chase.
+
+Options:
+  --base ADDRESS        Address of the first element
+  --element-size SIZE   Size of an element, e.g. 8B
+  --count N             Number of elements, or rows and columns of the matrices
+  --stride N            Stride of the strided pattern in elements
+  --block N             Block size of the matrix multiplication
+  --accesses N          Number of accesses of the random patterns
+  --exponent X          Exponent of the Zipf distribution
+  --seed N              Seed of the random patterns
+  --cache SPEC          Write a header that describes this cache
+",
+            false,
+            false,
+        ),
+        "exercise" => (
+            "Usage: cachecache exercise [OPTIONS]
+
+Prints a randomized exercise, or its solution, as Markdown or LaTeX. The largest cache within the
+bounds may have at most 4096 lines.
+
+Options:
+  --seed N              Seed of the exercise, random by default
+  --accesses N          Number of accesses (default: 12)
+  --address-bits N      Address size in bits
+  --min-sets N, --max-sets N      Bounds of the number of sets
+  --min-assoc N, --max-assoc N    Bounds of the associativity
+  --min-line SIZE, --max-line SIZE  Bounds of the line size
+  --strategies LIST     Replacement strategies to choose from, e.g. LRU,LFU
+  --output-format FORMAT  markdown or latex (default: markdown)
+  --solution            Print the solution instead of the task
+  --trace FILE          Also write the trace of the exercise
+",
+            false,
+            false,
+        ),
+        "grade" => (
+            "Usage: cachecache grade [OPTIONS] TRACE ANSWERS...
+
+Grades answer files against the first level of the simulated trace. The format of answer files
+is described at the top of src/grade/mod.rs. Files that cannot be graded are reported and make
+the command fail after the others are graded.
+
+Options:
+  --output-format FORMAT  text, json or csv (default: text)
+",
+            true,
+            true,
+        ),
+        "help" => ("Usage: cachecache help [COMMAND]\n", false, false),
+        _ => return None,
+    };
+
+    let mut help = usage.to_string();
+    if trace_options {
+        help.push_str(TRACE_OPTIONS);
+    }
+    if overrides {
+        help.push_str(OVERRIDE_OPTIONS);
+    }
+    Some(help)
+}
+
+/// Handles the command line, `args` includes the program name. Everything but opening the
+/// window happens here.
+pub fn run(mut args: Vec<String>) -> Outcome {
+    let program = if args.is_empty() { "cachecache".to_string() } else { args.remove(0) };
+
+    // The old options came first, with the arguments of the command after them. Anywhere else,
+    // they are options of a command, which reports them if it does not know them.
+    let legacy = args.first().and_then(|first| match first.as_str() {
+        "--no-window" => Some("simulate"),
+        "--convert" => Some("convert"),
+        _ => None,
+    });
+    let command = match (legacy, args.first()) {
+        (Some(command), _) => {
+            args.remove(0);
+            command.to_string()
+        }
+        (None, Some(first)) if COMMANDS.contains(&first.as_str()) => args.remove(0),
+        // Anything else than an existing file is most likely a mistyped command.
+        (None, Some(first)) if !first.starts_with('-') && !Path::new(first).exists() => {
+            eprintln!("{}", InvalidArgumentsError::UnknownCommand(first.clone()));
+            eprintln!("Run \"cachecache help\" for the list of commands.");
+            return Outcome::Exit(EXIT_USAGE);
+        }
+        // The window, but `--help` without a command lists all of them.
+        _ => String::new(),
+    };
+
+    if command == "help" || args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        let topic = if command == "help" { args.first().map_or("", String::as_str) } else { command.as_str() };
+        match help(topic) {
+            Some(help) => print!("{}", help),
+            None if topic.is_empty() => print!("{}", HELP),
+            None => {
+                eprintln!("{}", InvalidArgumentsError::UnknownCommand(topic.to_string()));
+                return Outcome::Exit(EXIT_USAGE);
+            }
+        }
+        return Outcome::Exit(0);
+    }
+
+    let command = if command.is_empty() { "gui".to_string() } else { command };
+    let args_for_gui = args.clone();
+    let args = Args::new(args);
+    let result = match command.as_str() {
+        "gui" => gui_options(args).map(|_| ()).map_err(Into::into),
+        "simulate" => simulate(args),
+        "sweep" => sweep(args),
+        "analyze" => analyze(args),
+        "repl" => repl(args),
+        "convert" => convert(args),
+        "generate" => generate_trace(args),
+        "exercise" => generate_exercise(args),
+        "grade" => grade_answers(args),
+        _ => unreachable!("Unknown commands are reported above"),
+    };
+
+    match result {
+        Ok(()) if command == "gui" => Outcome::Gui([program].into_iter().chain(args_for_gui).collect()),
+        Ok(()) => Outcome::Exit(0),
+        Err(err) => {
+            eprintln!("{}", err);
+            if err.is::<InvalidArgumentsError>() {
+                eprintln!("Run \"cachecache help {}\" for the usage.", command);
+                Outcome::Exit(EXIT_USAGE)
+            } else {
+                Outcome::Exit(EXIT_FAILURE)
+            }
+        }
+    }
+}
+
+/// Takes the options that select the trace format and the cache, without the overrides.
+fn source_options(args: &mut Args) -> Result<SimOptions, InvalidArgumentsError> {
+    Ok(SimOptions {
+        strict: args.flag("--strict"),
+        config_path: args.option("--config")?.map(PathBuf::from),
+        format: args.parse("--format")?,
+        overrides: CacheOverrides {
+            cache: args.parse_with("--cache", CacheDesc::from_spec)?,
+            ..CacheOverrides::default()
+        },
+    })
+}
+
+/// Takes the options of `source_options` and the overrides of every field of the cache.
+fn sim_options(args: &mut Args) -> Result<SimOptions, InvalidArgumentsError> {
+    let mut options = source_options(args)?;
+    options.overrides.addr_size = args.parse("--address-size")?;
+    options.overrides.block_size = args.parse("--block-size")?;
+    options.overrides.n_blocks = args.parse("--block-count")?;
+    options.overrides.assoc = args.parse("--assoc")?;
+    options.overrides.strategy = args.parse("--strategy")?;
+    Ok(options)
+}
+
+/// The options of the window and the trace to select, if any.
+pub fn gui_options(mut args: Args) -> Result<(SimOptions, Option<PathBuf>), InvalidArgumentsError> {
+    let options = sim_options(&mut args)?;
+    let path = args.finish(&[], 1)?.pop().map(PathBuf::from);
+    Ok((options, path))
+}
+
+/// Writes the HTML report of a simulated trace, titled with the path of the trace.
+pub fn write_report(report_path: &Path, trace_path: &Path, levels: &[LevelResult]) -> io::Result<()> {
+    let title = trace_path.display().to_string();
+    export::write_report(&mut BufWriter::new(File::create(report_path)?), &title, levels)
+}
+
+fn simulate(mut args: Args) -> Result<(), Box<dyn Error>> {
+    let options = sim_options(&mut args)?;
+    let no_history = args.flag("--no-history");
+    let events = args.flag("--events");
+    let report_path = args.option("--report")?.map(PathBuf::from);
+    let output_format = args.parse("--output-format")?.unwrap_or(OutputFormat::Text);
+    let blanks = args.parse_with("--blank", Blank::parse_list)?.unwrap_or_default();
+    let watch = args.flag("--watch");
+    let path = PathBuf::from(&args.finish(&["TRACE"], 1)?[0]);
+
+    if watch && path == Path::new("-") {
+        return Err(InvalidArgumentsError::Conflict("--watch needs a trace file, not stdin").into());
+    }
+    if events && output_format != OutputFormat::Text {
+        return Err(InvalidArgumentsError::Conflict("--events only applies to the text output").into());
+    }
+    if !blanks.is_empty() && !output_format.has_tables() {
+        return Err(InvalidArgumentsError::Conflict("--blank only applies to --output-format latex or markdown").into());
+    }
+    if (events || report_path.is_some() || output_format.has_tables()) && no_history {
+        return Err(InvalidArgumentsError::Conflict(
+            "--events, --report and tables need the history of the simulation and cannot be combined with --no-history",
+        )
+        .into());
+    }
+
+    let print = || -> Result<(), Box<dyn Error>> {
+        let levels = run_sim(&path, &options, !no_history)?;
+        if let Some(report_path) = &report_path {
+            write_report(report_path, &path, &levels)
+                .map_err(|err| format!("Could not write {}: {}", report_path.display(), err))?;
+        }
+
+        let mut stdout = BufWriter::new(io::stdout().lock());
+        if events {
+            export::write_events(&mut stdout, &levels)?;
+        }
+        export::write(&mut stdout, output_format, &levels, &blanks)?;
+        Ok(())
+    };
+    if !watch {
+        return print();
+    }
+
+    // Until interrupted, mistakes in the trace are reported and can be fixed in the meantime.
+    let watched: Vec<&Path> = [Some(path.as_path()), options.config_path.as_deref()].into_iter().flatten().collect();
+    let mut stamps = file_stamps(&watched);
+    loop {
+        if let Err(err) = print() {
+            eprintln!("{}", err);
+        }
+        let old_stamps = stamps;
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+            stamps = file_stamps(&watched);
+            if stamps != old_stamps {
+                break;
+            }
+        }
+        let changed: Vec<String> = watched
+            .iter()
+            .zip(stamps.iter().zip(&old_stamps))
+            .filter(|(_, (new, old))| new != old)
+            .map(|(path, _)| path.display().to_string())
+            .collect();
+        eprintln!("{} changed, simulating again", changed.join(" and "));
+    }
+}
+
+/// How often `--watch` checks whether the files changed.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The modification time and size of every file, `None` for files that cannot be read, e.g.
+/// because an editor is replacing them.
+fn file_stamps(paths: &[&Path]) -> Vec<Option<(SystemTime, u64)>> {
+    paths
+        .iter()
+        .map(|path| fs::metadata(path).and_then(|metadata| Ok((metadata.modified()?, metadata.len()))).ok())
+        .collect()
+}
+
+fn sweep(mut args: Args) -> Result<(), Box<dyn Error>> {
+    let options = source_options(&mut args)?;
+    let sweep = Sweep {
+        addr_sizes: args.list_with("--address-size", str::parse::<u64>)?.unwrap_or_default(),
+        block_sizes: args.list_with("--block-size", str::parse::<u64>)?.unwrap_or_default(),
+        n_blocks: args.list_with("--block-count", str::parse::<u64>)?.unwrap_or_default(),
+        assocs: args.list_with("--assoc", str::parse::<u64>)?.unwrap_or_default(),
+        strategies: args.list_with("--strategy", str::parse::<Strategy>)?.unwrap_or_default(),
+    };
+    let output_format = args.parse("--output-format")?.unwrap_or(OutputFormat::Text);
+    let path = PathBuf::from(&args.finish(&["TRACE"], 1)?[0]);
+    if output_format.has_tables() {
+        return Err(InvalidArgumentsError::Conflict("Sweeps can only be written as text, json or csv").into());
+    }
+
+    let (levels, accesses) = open_trace(&path, &options)?;
+    let (caches, skipped) = sweep.caches(&levels[0].cache);
+    for message in &skipped {
+        eprintln!("Skipping {}", message);
+    }
+    if caches.is_empty() {
+        return Err("None of the combinations describes a cache".into());
+    }
+
+    let stats = sweep::run(accesses, &caches)?;
+    sweep::write(&mut BufWriter::new(io::stdout().lock()), output_format, &caches, &stats)?;
+
+    Ok(())
+}
+
+fn analyze(mut args: Args) -> Result<(), Box<dyn Error>> {
+    let options = source_options(&mut args)?;
+    let block_size: Option<u64> = args.parse("--block-size")?;
+    let output_format = args.parse("--output-format")?.unwrap_or(OutputFormat::Text);
+    let path = PathBuf::from(&args.finish(&["TRACE"], 1)?[0]);
+    if !matches!(output_format, OutputFormat::Text | OutputFormat::Json) {
+        return Err(InvalidArgumentsError::Conflict("Analyses can only be written as text or json").into());
+    }
+
+    let config = options.config_path.as_deref().map(load_config).transpose()?;
+    let strict = options.strict || config.as_ref().is_some_and(|config| config.strict);
+    let trace = trace::open(&path, options.format, strict)?;
+
+    // Unlike a simulation, the analysis does not need a cache, only the line size.
+    let cache = options.overrides.cache.clone()
+        .or_else(|| config.map(|mut config| config.levels.swap_remove(0).cache))
+        .or_else(|| trace.cache.as_ref().ok().cloned());
+    let offset_bits = block_size.or(cache.map(|cache| cache.offset_bits())).unwrap_or(6);
+    if offset_bits >= u64::from(u64::BITS) {
+        return Err(format!("Blocks of {} bits are too large", offset_bits).into());
+    }
+
+    let analysis = Analysis::run(trace, offset_bits)?;
+    let mut stdout = BufWriter::new(io::stdout().lock());
+    match output_format {
+        OutputFormat::Json => analysis.write_json(&mut stdout)?,
+        _ => analysis.write_text(&mut stdout)?,
+    }
+    stdout.flush()?;
+
+    Ok(())
+}
+
+fn repl(mut args: Args) -> Result<(), Box<dyn Error>> {
+    let options = sim_options(&mut args)?;
+    let path = args.finish(&[], 1)?.pop().map(PathBuf::from);
+
+    let mut repl = match &path {
+        Some(path) => {
+            let (mut levels, accesses) = open_trace(path, &options)?;
+            let cache = levels.swap_remove(0).cache;
+            let mut addrs = Vec::new();
+            for access in accesses {
+                addrs.extend(access?.line_addrs(cache.offset_bits()));
+            }
+
+            let mut repl = Repl::new(cache);
+            repl.access(addrs);
+            repl
+        }
+        None => {
+            let cache = match (&options.config_path, &options.overrides.cache) {
+                (Some(config_path), _) => load_config(config_path)?.levels.swap_remove(0).cache,
+                (None, Some(cache)) => cache.clone(),
+                (None, None) => {
+                    return Err(InvalidArgumentsError::Conflict("The cache has to be given with --cache, --config or a trace").into())
+                }
+            };
+            Repl::new(options.overrides.apply(&cache)?)
+        }
+    };
+
+    let stdin = io::stdin();
+    let interactive = stdin.is_terminal();
+    repl.run(stdin.lock(), &mut io::stdout().lock(), interactive)
+}
+
+pub fn load_config(path: &Path) -> Result<Config, Box<dyn Error>> {
+    Ok(Config::load(path).map_err(|err| format!("{}: {}", path.display(), err))?)
+}
+
+/// Converts a trace of any format to the compact binary format, keeping the cache it describes.
+fn convert(mut args: Args) -> Result<(), Box<dyn Error>> {
+    let strict = args.flag("--strict");
+    let format = args.parse("--format")?;
+    let paths = args.finish(&["INPUT", "OUTPUT"], 2)?;
+    let trace = trace::open(Path::new(&paths[0]), format, strict)?;
+
+    let mut writer = trace::binary::Writer::new(BufWriter::new(File::create(&paths[1])?), trace.cache.ok().as_ref())?;
+    for access in trace.accesses {
+        writer.write(&access?)?;
+    }
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Takes the options of a synthetic trace, everything but the pattern.
+pub fn workload_options(args: &mut Args) -> Result<Workload, InvalidArgumentsError> {
+    let mut workload = Workload::default();
+
+    if let Some(base) = args.parse_with("--base", |base| {
+        generate::parse_address(base).ok_or("Expected a decimal or hexadecimal address")
+    })? {
+        workload.base = base;
+    }
+    if let Some(size) = args.parse_with("--element-size", sim::parse_size)? {
+        workload.element_size = size;
+    }
+    for (option, value) in [
+        ("--count", &mut workload.count),
+        ("--stride", &mut workload.stride),
+        ("--block", &mut workload.block),
+        ("--seed", &mut workload.seed),
+    ] {
+        if let Some(parsed) = args.parse(option)? {
+            *value = parsed;
+        }
+    }
+    workload.accesses = args.parse("--accesses")?;
+    if let Some(exponent) = args.parse("--exponent")? {
+        workload.exponent = exponent;
+    }
+
+    Ok(workload)
+}
+
+/// Writes a synthetic trace to the path after the pattern, or to stdout without one.
+fn generate_trace(mut args: Args) -> Result<(), Box<dyn Error>> {
+    let mut workload = workload_options(&mut args)?;
+    let cache = args.parse_with("--cache", CacheDesc::from_spec)?;
+
+    let positional = args.finish(&["PATTERN"], 2)?;
+    workload.pattern = positional[0].parse()?;
+    let addrs = workload.addrs()?;
+
+    match positional.get(1).filter(|path| *path != "-") {
+        Some(path) => sim::write(&mut BufWriter::new(File::create(path)?), cache.as_ref(), addrs)?,
+        None => sim::write(&mut BufWriter::new(io::stdout().lock()), cache.as_ref(), addrs)?,
+    }
+
+    Ok(())
+}
+
+/// Prints a randomized exercise, or its solution with `--solution`.
+fn generate_exercise(mut args: Args) -> Result<(), Box<dyn Error>> {
+    let mut bounds = Bounds::default();
+
+    let solution = args.flag("--solution");
+    let trace_path = args.option("--trace")?;
+    for (option, value) in [
+        ("--address-bits", &mut bounds.addr_size),
+        ("--min-sets", &mut bounds.min_sets),
+        ("--max-sets", &mut bounds.max_sets),
+        ("--min-assoc", &mut bounds.min_assoc),
+        ("--max-assoc", &mut bounds.max_assoc),
+    ] {
+        if let Some(parsed) = args.parse(option)? {
+            *value = parsed;
+        }
+    }
+    for (option, value) in [("--min-line", &mut bounds.min_line), ("--max-line", &mut bounds.max_line)] {
+        if let Some(size) = args.parse_with(option, sim::parse_size)? {
+            *value = size;
+        }
+    }
+    if let Some(strategies) = args.list_with("--strategies", str::parse::<Strategy>)? {
+        bounds.strategies = strategies;
+    }
+    let accesses = args.parse("--accesses")?.unwrap_or(12);
+    // Without a seed, every exercise is different. The seed is printed, so it can be repeated.
+    let seed = match args.parse("--seed")? {
+        Some(seed) => seed,
+        None => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64),
+    };
+    let format = args.parse("--output-format")?.unwrap_or(OutputFormat::Markdown);
+    args.finish(&[], 0)?;
+    if !format.has_tables() {
+        return Err(InvalidArgumentsError::Conflict("Exercises can only be written as markdown or latex").into());
+    }
+
+    let exercise = Exercise::generate(&bounds, accesses, seed).map_err(|err| -> Box<dyn Error> {
+        match err {
+            // Any other error is due to the bounds or the number of accesses on the command line.
+            ExerciseError::NotFound => err.into(),
+            _ => InvalidArgumentsError::Unsatisfiable(err.to_string()).into(),
+        }
+    })?;
+    if let Some(trace_path) = trace_path {
+        sim::write(&mut BufWriter::new(File::create(trace_path)?), Some(&exercise.cache), exercise.addrs.iter().copied())?;
+    }
+    exercise.write(&mut BufWriter::new(io::stdout().lock()), format, solution)?;
+
+    Ok(())
+}
+
+/// Grades answer files against the first level of the simulated trace and prints the grades.
+/// Files that cannot be graded are reported with their error and make the command fail.
+fn grade_answers(mut args: Args) -> Result<(), Box<dyn Error>> {
+    let options = sim_options(&mut args)?;
+    let output_format = args.parse("--output-format")?.unwrap_or(OutputFormat::Text);
+    let paths = args.finish(&["TRACE", "ANSWERS"], usize::MAX)?;
+    if output_format.has_tables() {
+        return Err(InvalidArgumentsError::Conflict("Grades can only be written as text, json or csv").into());
+    }
+
+    let trace_path = PathBuf::from(&paths[0]);
+    let level = run_sim(&trace_path, &options, true)?.swap_remove(0);
+    let grades: Vec<_> = paths[1..]
+        .iter()
+        .map(|path| {
+            let grade = File::open(path)
+                .map_err(AnswerError::from)
+                .and_then(|file| Answers::read(io::BufReader::new(file)))
+                .and_then(|answers| answers.grade(&level));
+            if let Err(err) = &grade {
+                eprintln!("{}: {}", path, err);
+            }
+            (path.clone(), grade)
+        })
+        .collect();
+
+    let trace = trace_path.display().to_string();
+    grade::write(&mut BufWriter::new(io::stdout().lock()), output_format, &trace, &level.name, &grades)?;
+
+    let failed = grades.iter().filter(|(_, grade)| grade.is_err()).count();
+    if failed > 0 {
+        return Err(format!("{} of {} answer files could not be graded", failed, grades.len()).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,382 @@
+// Cache configuration files in TOML or JSON that can be used together with any trace file.
+//
+// Version 1 of the format describes either a single cache:
+//
+//     version = 1
+//
+//     [cache]
+//     size = "32KiB"
+//     line = "64B"
+//     associativity = "8-way"
+//     policy = "LRU"
+//     address_bits = 48
+//
+// or a hierarchy of caches, first level first, where each level sees the misses of the previous one:
+//
+//     version = 1
+//
+//     [[levels]]
+//     name = "L1"
+//     spec = "32KiB, 64B line, 8-way, LRU, 48-bit"
+//
+//     [[levels]]
+//     name = "L2"
+//     block_bits = 6
+//     blocks = 4096
+//     associativity = 16
+//
+// A level is described by a `spec` in the notation of trace headers, by `size` and `line`
+// (strings like "32KiB" or numbers of bytes), or by `block_bits` and `blocks` like the five line
+// trace header. `associativity` is a number of ways or a string like "direct-mapped". The
+// optional `[options]` table currently only contains `strict`, which rejects malformed address
+// lines in the trace.
+
+use std::error::Error;
+use std::path::Path;
+use std::{fmt, fs, io};
+
+use serde::Deserialize;
+
+use crate::sim::{self, CacheDesc, GeometryError, SpecError, Strategy};
+
+/// The newest version of the configuration format.
+pub const VERSION: u32 = 1;
+
+pub struct Level {
+    pub name: String,
+    pub cache: CacheDesc,
+}
+
+pub struct Config {
+    pub levels: Vec<Level>,
+    pub strict: bool,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    UnsupportedVersion(u32),
+    NoCache,
+    CacheAndLevels,
+    /// A level mixes the ways of describing it or leaves out parts of them.
+    IncompleteLevel { level: String },
+    InvalidPolicy { level: String, text: String },
+    InvalidSize { level: String, err: SpecError },
+    InvalidAssoc { level: String, text: String },
+    Spec { level: String, err: SpecError },
+    Geometry { level: String, err: GeometryError },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "{}", err),
+            ConfigError::Toml(err) => write!(f, "Invalid TOML: {}", err),
+            ConfigError::Json(err) => write!(f, "Invalid JSON: {}", err),
+            ConfigError::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported configuration version {}, the newest supported version is {}",
+                version, VERSION
+            ),
+            ConfigError::NoCache => write!(f, "The configuration contains neither a cache nor levels"),
+            ConfigError::CacheAndLevels => {
+                write!(f, "The configuration may only contain either a cache or levels")
+            }
+            ConfigError::IncompleteLevel { level } => write!(
+                f,
+                "{}: Describe the level either with only a spec, with size, line and associativity, \
+                 or with block_bits, blocks and associativity",
+                level
+            ),
+            ConfigError::InvalidPolicy { level, text } => write!(
+                f,
+                "{}: Unknown policy \"{}\", expected LRU, LFU or First",
+                level, text
+            ),
+            ConfigError::InvalidSize { level, err } => write!(f, "{}: {}", level, err),
+            ConfigError::InvalidAssoc { level, text } => write!(
+                f,
+                "{}: \"{}\" is not an associativity like 8-way, direct-mapped or fully-associative",
+                level, text
+            ),
+            ConfigError::Spec { level, err } => write!(f, "{}: {}", level, err),
+            ConfigError::Geometry { level, err } => write!(f, "{}: {}", level, err),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io(err) => Some(err),
+            ConfigError::Toml(err) => Some(err),
+            ConfigError::Json(err) => Some(err),
+            ConfigError::InvalidSize { err, .. } | ConfigError::Spec { err, .. } => Some(err),
+            ConfigError::Geometry { err, .. } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+// The structs below mirror the file format. They are turned into `Config` after parsing.
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    version: u32,
+    cache: Option<RawLevel>,
+    #[serde(default)]
+    levels: Vec<RawLevel>,
+    #[serde(default)]
+    options: RawOptions,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RawOptions {
+    #[serde(default)]
+    strict: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawLevel {
+    name: Option<String>,
+    spec: Option<String>,
+    size: Option<RawSize>,
+    line: Option<RawSize>,
+    block_bits: Option<u64>,
+    blocks: Option<u64>,
+    associativity: Option<RawAssoc>,
+    policy: Option<String>,
+    address_bits: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawSize {
+    Bytes(u64),
+    Text(String),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawAssoc {
+    Ways(u64),
+    Text(String),
+}
+
+impl Config {
+    /// Loads a configuration file. Files ending in `.json` are read as JSON, all others as TOML.
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        let content = fs::read_to_string(path)?;
+
+        let is_json = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        Config::parse(&content, is_json)
+    }
+
+    fn parse(content: &str, is_json: bool) -> Result<Config, ConfigError> {
+        let raw: RawConfig = if is_json {
+            serde_json::from_str(content).map_err(ConfigError::Json)?
+        } else {
+            toml::from_str(content).map_err(ConfigError::Toml)?
+        };
+
+        if raw.version != VERSION {
+            return Err(ConfigError::UnsupportedVersion(raw.version));
+        }
+
+        let raw_levels = match (raw.cache, raw.levels.is_empty()) {
+            (Some(cache), true) => vec![cache],
+            (None, false) => raw.levels,
+            (None, true) => return Err(ConfigError::NoCache),
+            (Some(_), false) => return Err(ConfigError::CacheAndLevels),
+        };
+
+        let levels = raw_levels
+            .into_iter()
+            .enumerate()
+            .map(|(i, raw)| raw.into_level(i))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Config {
+            levels,
+            strict: raw.options.strict,
+        })
+    }
+}
+
+impl RawLevel {
+    fn into_level(self, index: usize) -> Result<Level, ConfigError> {
+        let name = self.name.unwrap_or_else(|| format!("L{}", index + 1));
+        // A spec already contains everything else.
+        let only_spec = self.associativity.is_none()
+            && self.policy.is_none()
+            && self.address_bits.is_none();
+
+        let strat = match self.policy {
+            Some(text) => text.parse().map_err(|_| ConfigError::InvalidPolicy {
+                level: name.clone(),
+                text,
+            })?,
+            None => Strategy::LRU,
+        };
+        let addr_size = self.address_bits.unwrap_or(sim::DEFAULT_ADDR_SIZE);
+
+        // None for fully associative caches, like sim::parse_assoc.
+        let assoc = match self.associativity {
+            Some(RawAssoc::Ways(ways)) => Some(Some(ways)),
+            Some(RawAssoc::Text(text)) => Some(sim::parse_assoc(&text).ok_or_else(|| {
+                ConfigError::InvalidAssoc {
+                    level: name.clone(),
+                    text,
+                }
+            })?),
+            None => None,
+        };
+
+        let incomplete = || ConfigError::IncompleteLevel { level: name.clone() };
+
+        let cache = match (self.spec, self.size, self.line, self.block_bits, self.blocks) {
+            (Some(spec), None, None, None, None) if only_spec => {
+                CacheDesc::from_spec(&spec).map_err(|err| ConfigError::Spec {
+                    level: name.clone(),
+                    err,
+                })?
+            }
+            (None, Some(size), Some(line), None, None) => {
+                let to_bytes = |size: RawSize| match size {
+                    RawSize::Bytes(bytes) => Ok(bytes),
+                    RawSize::Text(text) => sim::parse_size(&text),
+                };
+                let invalid_size = |err| ConfigError::InvalidSize {
+                    level: name.clone(),
+                    err,
+                };
+                let size = to_bytes(size).map_err(invalid_size)?;
+                let line = to_bytes(line).map_err(invalid_size)?;
+
+                CacheDesc::from_sizes(size, line, assoc.ok_or_else(incomplete)?, strat, addr_size)
+                    .map_err(|err| ConfigError::Spec {
+                        level: name.clone(),
+                        err,
+                    })?
+            }
+            (None, None, None, Some(block_bits), Some(blocks)) => {
+                let assoc = assoc.ok_or_else(incomplete)?.unwrap_or(blocks);
+                CacheDesc::new(addr_size, block_bits, blocks, assoc, strat).map_err(|err| {
+                    ConfigError::Geometry {
+                        level: name.clone(),
+                        err,
+                    }
+                })?
+            }
+            _ => return Err(incomplete()),
+        };
+
+        Ok(Level { name, cache })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn example(name: &str) -> Config {
+        Config::load(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test").join(name)).unwrap()
+    }
+
+    fn specs(config: &Config) -> Vec<(&str, String)> {
+        config.levels.iter().map(|level| (level.name.as_str(), level.cache.spec())).collect()
+    }
+
+    fn error(content: &str) -> ConfigError {
+        match Config::parse(content, false) {
+            Err(err) => err,
+            Ok(_) => panic!("{} was accepted", content),
+        }
+    }
+
+    #[test]
+    fn single_cache() {
+        let config = example("config.toml");
+        assert_eq!(specs(&config), [("L1", "256B, 16B line, 4-way, LRU, 12-bit".to_string())]);
+        assert!(!config.strict);
+    }
+
+    #[test]
+    fn hierarchy() {
+        let config = example("hierarchy.json");
+        assert_eq!(
+            specs(&config),
+            [
+                ("L1", "64B, 16B line, direct-mapped, LRU, 12-bit".to_string()),
+                ("L2", "256B, 16B line, 4-way, LRU, 12-bit".to_string()),
+            ]
+        );
+        assert!(config.strict);
+    }
+
+    #[test]
+    fn block_bits() {
+        let config = Config::parse(
+            "version = 1\n[[levels]]\nname = \"L3\"\nblock_bits = 6\nblocks = 64\nassociativity = \"fully-associative\"\npolicy = \"LFU\"",
+            false,
+        )
+        .unwrap();
+        assert_eq!(specs(&config), [("L3", "4KiB, 64B line, fully-associative, LFU, 32-bit".to_string())]);
+    }
+
+    #[test]
+    fn unknown_key() {
+        assert!(matches!(error("version = 1\nstrict = true\n[cache]\nspec = \"1KiB, 64B line, 2-way\""), ConfigError::Toml(_)));
+        assert!(matches!(error("version = 1\n[cache]\nspec = \"1KiB, 64B line, 2-way\"\nways = 2"), ConfigError::Toml(_)));
+        assert!(matches!(
+            Config::parse(r#"{ "version": 1, "cache": { "spec": "1KiB, 64B line, 2-way" }, "verbose": true }"#, true),
+            Err(ConfigError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn unsupported_version() {
+        assert!(matches!(error("version = 2\n[cache]\nspec = \"1KiB, 64B line, 2-way\""), ConfigError::UnsupportedVersion(2)));
+        assert!(matches!(error("[cache]\nspec = \"1KiB, 64B line, 2-way\""), ConfigError::Toml(_)));
+    }
+
+    #[test]
+    fn conflicting_fields() {
+        let incomplete = |content: &str| match error(content) {
+            ConfigError::IncompleteLevel { level } => level,
+            err => panic!("unexpected error {}", err),
+        };
+
+        // A spec with any other field.
+        assert_eq!(incomplete("version = 1\n[cache]\nspec = \"1KiB, 64B line, 2-way\"\nsize = \"1KiB\""), "L1");
+        assert_eq!(incomplete("version = 1\n[cache]\nspec = \"1KiB, 64B line, 2-way\"\nassociativity = 2"), "L1");
+        // Sizes together with block bits.
+        assert_eq!(
+            incomplete("version = 1\n[[levels]]\nspec = \"64B, 16B line, 1-way\"\n[[levels]]\nname = \"L2\"\nsize = 1024\nline = 64\nblocks = 16\nassociativity = 2"),
+            "L2"
+        );
+        // Sizes without associativity.
+        assert_eq!(incomplete("version = 1\n[cache]\nsize = 1024\nline = 64"), "L1");
+
+        assert!(matches!(error("version = 1"), ConfigError::NoCache));
+        assert!(matches!(
+            error("version = 1\n[cache]\nspec = \"1KiB, 64B line, 2-way\"\n[[levels]]\nspec = \"1KiB, 64B line, 2-way\""),
+            ConfigError::CacheAndLevels
+        ));
+    }
+}
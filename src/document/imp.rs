@@ -0,0 +1,58 @@
+use std::cell::{Cell, OnceCell, RefCell};
+use std::path::PathBuf;
+
+use gtk::gio::FileMonitor;
+use gtk::glib::{self, Properties, Sender};
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use libadwaita::{Toast, ToastOverlay};
+
+use crate::SimOptions;
+use super::SimulationCommunication;
+
+#[derive(Properties, Default)]
+#[properties(wrapper_type = super::CacheCacheDocument)]
+pub struct CacheCacheDocument {
+    #[property(get, set)]
+    pub path_buf: RefCell<PathBuf>,
+    /// Configuration file describing the cache instead of the trace header. Empty if unused.
+    #[property(get, set)]
+    pub config_path: RefCell<PathBuf>,
+    /// Whether a simulation is running.
+    #[property(get)]
+    pub running: Cell<bool>,
+    /// The options of the command line with the overrides of the header bar.
+    pub options: RefCell<SimOptions>,
+    /// Whether the inputs changed while a simulation was running, which then has to be repeated.
+    pub rerun: Cell<bool>,
+    pub monitors: RefCell<Vec<FileMonitor>>,
+    pub toast_overlay: ToastOverlay,
+    /// The toast of the last failed run. It is kept until the next run so the message can be read.
+    pub error_toast: RefCell<Option<Toast>>,
+    pub sim_sender: OnceCell<Sender<SimulationCommunication>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for CacheCacheDocument {
+    const NAME: &'static str = "CacheCacheDocument";
+    type Type = super::CacheCacheDocument;
+    type ParentType = gtk::Box;
+}
+
+impl ObjectImpl for CacheCacheDocument {
+    fn properties() -> &'static [glib::ParamSpec] {
+        Self::derived_properties()
+    }
+
+    fn set_property(&self, id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        self.derived_set_property(id, value, pspec)
+    }
+
+    fn property(&self, id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        self.derived_property(id, pspec)
+    }
+}
+
+impl WidgetImpl for CacheCacheDocument {}
+
+impl BoxImpl for CacheCacheDocument {}
@@ -0,0 +1,458 @@
+// A trace opened in a tab of the window, with its own configuration, overrides and simulation.
+// Documents run their simulations independently and keep working when their tab is moved to
+// another window.
+
+mod imp;
+
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::thread;
+
+use gtk::gio::{self, Cancellable, FileMonitor, FileMonitorEvent, FileMonitorFlags};
+use gtk::glib::{self, clone, BoxedAnyObject, MainContext, Object, Priority};
+use gtk::pango::EllipsizeMode;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{Align, Button, ColumnView, ColumnViewColumn, DropDown, Label, ListItem, Orientation, Paned, PolicyType};
+use gtk::{ScrolledWindow, Separator, SignalListItemFactory, SingleSelection, Spinner, StringList};
+use libadwaita::{Toast, ToastPriority};
+
+use crate::event_model::{EventModel, EventRow};
+use crate::result_model::{LineRow, ResultModel};
+use crate::sim::{AccessEvent, CacheDesc, CacheOverrides, CacheStats, LevelResult};
+use crate::{run_sim, CacheLineVec, SimOptions, SimResult};
+
+/// Lines, accesses and associativity of a cache level, as needed by the result views.
+type LevelLines = (Rc<CacheLineVec>, Rc<Vec<AccessEvent>>, u64);
+
+pub enum SimulationCommunication {
+    Success(SimResult),
+    Failure(String),
+}
+
+glib::wrapper! {
+    pub struct CacheCacheDocument(ObjectSubclass<imp::CacheCacheDocument>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl CacheCacheDocument {
+    /// Creates a document for the trace at `path`, or an empty one that waits for a trace. The
+    /// configuration of `options` is shown and can be changed like the rest of the document.
+    pub fn new(options: SimOptions, path: Option<PathBuf>) -> Self {
+        let document: Self = Object::builder()
+            .property("orientation", Orientation::Vertical)
+            .build();
+        if let Some(config_path) = &options.config_path {
+            document.set_config_path(config_path.clone());
+        }
+        if let Some(path) = path {
+            document.set_path_buf(path);
+        }
+        document.imp().options.replace(options);
+        document.build();
+        document.watch();
+        document
+    }
+
+    fn build(&self) {
+        let imp = self.imp();
+        let toast_overlay = &imp.toast_overlay;
+
+        let scrolled_window = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Automatic)
+            .min_content_width(150)
+            .vexpand(true)
+            .build();
+
+        let result_model = ResultModel::new();
+        let line_selection = SingleSelection::builder()
+            .model(&result_model)
+            .autoselect(false)
+            .can_unselect(true)
+            .build();
+        let result_view = ColumnView::builder()
+            .model(&line_selection)
+            .show_row_separators(true)
+            .show_column_separators(true)
+            .build();
+
+        result_view.append_column(&result_column("Set", false, |row: &LineRow| row.set().to_string()));
+        result_view.append_column(&result_column("Way", false, |row: &LineRow| row.way().to_string()));
+        result_view.append_column(&result_column("Tag", false, |row: &LineRow| {
+            row.current().map_or("-".to_string(), |entry| format!("{:x}", entry.tag()))
+        }));
+        result_view.append_column(&result_column("Entered", false, |row: &LineRow| {
+            row.current().map_or("-".to_string(), |entry| entry.entered().to_string())
+        }));
+        result_view.append_column(&result_column("History", true, LineRow::history));
+
+        scrolled_window.set_child(Some(&result_view));
+
+        // Every access of the displayed level. Selecting one selects the line it hit or filled.
+        let event_model = EventModel::new();
+        let event_selection = SingleSelection::builder()
+            .model(&event_model)
+            .autoselect(false)
+            .can_unselect(true)
+            .build();
+        let event_view = ColumnView::builder()
+            .model(&event_selection)
+            .show_row_separators(true)
+            .show_column_separators(true)
+            .build();
+
+        event_view.append_column(&result_column("Time", false, |row: &EventRow| row.event().time.to_string()));
+        event_view.append_column(&result_column("Address", false, |row: &EventRow| format!("{:x}", row.event().addr)));
+        event_view.append_column(&result_column("Tag", false, |row: &EventRow| format!("{:x}", row.event().tag)));
+        event_view.append_column(&result_column("Set", false, |row: &EventRow| row.event().set.to_string()));
+        event_view.append_column(&result_column("Offset", false, |row: &EventRow| row.event().offset.to_string()));
+        event_view.append_column(&result_column("Result", false, |row: &EventRow| {
+            if row.event().hit { "Hit" } else { "Miss" }.to_string()
+        }));
+        event_view.append_column(&result_column("Way", false, |row: &EventRow| row.event().way.to_string()));
+        event_view.append_column(&result_column("Evicted", true, |row: &EventRow| {
+            row.event().evicted.map_or("-".to_string(), |tag| format!("{:x}", tag))
+        }));
+
+        let event_scrolled_window = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Automatic)
+            .min_content_width(150)
+            .child(&event_view)
+            .build();
+
+        event_selection.connect_selected_notify(clone!(@weak event_model, @weak result_model, @weak line_selection, @weak result_view, @weak scrolled_window => move |event_selection| {
+            let Some(event) = event_model.event(event_selection.selected()) else {
+                return;
+            };
+            let index = event.set * result_model.assoc() + event.way;
+            line_selection.set_selected(index as u32);
+
+            // Rows have the same height, so the position of the line follows from its index. The
+            // header row at the top is scrolled as well.
+            let adjustment = scrolled_window.vadjustment();
+            let n_lines = f64::from(result_model.n_items().max(1));
+            let header = f64::from(result_view.first_child().map_or(0, |header| header.height()));
+            let position = header + (adjustment.upper() - header) * index as f64 / n_lines;
+            adjustment.set_value((position - adjustment.page_size() / 2.0).max(0.0));
+        }));
+
+        let result_paned = Paned::builder()
+            .orientation(Orientation::Horizontal)
+            .start_child(&scrolled_window)
+            .end_child(&event_scrolled_window)
+            .vexpand(true)
+            .build();
+
+        let separator_top = Separator::new(Orientation::Horizontal);
+        let separator_bottom = Separator::new(Orientation::Horizontal);
+        separator_bottom.set_visible(false);
+
+        let file_display_label = Label::builder()
+            .label("No File Selected")
+            .build();
+        let file_display_spinner = Spinner::builder()
+            .halign(Align::End)
+            .build();
+
+        self.bind_property("path-buf", &file_display_label, "label")
+            .transform_to(|_, path_buf: PathBuf| {
+                if path_buf.as_os_str().is_empty() {
+                    Some("No File Selected".to_value())
+                } else if let Some(file_str) = path_buf.to_str().to_owned() {
+                    Some(file_str.to_value())
+                } else if path_buf.is_file() {
+                    Some("Could not parse file name to string".to_value())
+                } else {
+                    Some("No File Selected".to_value())
+                }
+            })
+            .sync_create()
+            .build();
+        self.bind_property("running", &file_display_spinner, "spinning")
+            .sync_create()
+            .build();
+
+        let config_display_label = Label::builder()
+            .ellipsize(EllipsizeMode::Start)
+            .hexpand(true)
+            .halign(Align::End)
+            .build();
+        let clear_config_button = Button::builder()
+            .icon_name("edit-clear-symbolic")
+            .tooltip_text("Use the Trace Header Instead of the Configuration")
+            .build();
+
+        self.bind_property("config-path", &config_display_label, "label")
+            .transform_to(|_, config_path: PathBuf| {
+                Some(format!("Configuration: {}", config_path.display()).to_value())
+            })
+            .sync_create()
+            .build();
+        for widget in [config_display_label.upcast_ref::<gtk::Widget>(), clear_config_button.upcast_ref()] {
+            self.bind_property("config-path", widget, "visible")
+                .transform_to(|_, config_path: PathBuf| Some((!config_path.as_os_str().is_empty()).to_value()))
+                .sync_create()
+                .build();
+        }
+
+        clear_config_button.connect_clicked(clone!(@weak self as document => move |_| {
+            document.set_config_path(PathBuf::new());
+        }));
+
+        // Only shown for configurations with more than one level.
+        let level_dropdown = DropDown::builder()
+            .visible(false)
+            .tooltip_text("Displayed Cache Level")
+            .build();
+
+        let file_display = gtk::Box::builder()
+            .spacing(10)
+            .margin_top(10)
+            .margin_end(10)
+            .margin_start(10)
+            .margin_bottom(10)
+            .orientation(Orientation::Horizontal)
+            .hexpand(true)
+            .build();
+
+        file_display.append(&file_display_label);
+        file_display.append(&file_display_spinner);
+        file_display.append(&config_display_label);
+        file_display.append(&clear_config_button);
+        file_display.append(&level_dropdown);
+
+        let stats_showcase = Label::builder().visible(false).build();
+
+        stats_showcase.bind_property("visible", &separator_bottom, "visible")
+            .bidirectional()
+            .build();
+
+        let (sim_sender, sim_receiver) = MainContext::channel(Priority::default());
+        imp.sim_sender.set(sim_sender).expect("Documents are only built once");
+
+        let (stats_sender, stats_receiver) = MainContext::channel(Priority::default());
+
+        // Lines and associativity of every level of the last result, to switch between them.
+        let level_lines: Rc<RefCell<Vec<LevelLines>>> = Rc::default();
+
+        level_dropdown.connect_selected_notify(clone!(@weak result_model, @weak event_model, @strong level_lines => move |level_dropdown| {
+            if let Some((lines, events, assoc)) = level_lines.borrow().get(level_dropdown.selected() as usize) {
+                result_model.set_result(lines.clone(), *assoc);
+                event_model.set_events(events.clone());
+            }
+        }));
+
+        sim_receiver.attach(None, clone!(@weak self as document, @weak result_model, @weak event_model, @weak level_dropdown, @strong level_lines => @default-return Continue(false),
+            move |result| {
+                let imp = document.imp();
+                imp.running.set(false);
+                document.notify_running();
+                if imp.rerun.take() {
+                    document.simulate();
+                    return Continue(true);
+                }
+
+                match result {
+                    SimulationCommunication::Success(levels) => {
+                        let names: Vec<&str> = levels.iter().map(|level| level.name.as_str()).collect();
+                        let model = StringList::new(&names);
+
+                        let mut lines = Vec::with_capacity(levels.len());
+                        let mut level_stats = Vec::with_capacity(levels.len());
+                        for LevelResult { name, cache, lines: level, events, stats } in levels {
+                            lines.push((Rc::new(level), Rc::new(events), cache.assoc()));
+                            level_stats.push((name, cache, stats));
+                        }
+
+                        let (first_lines, first_events, assoc) = lines[0].clone();
+                        level_lines.replace(lines);
+                        level_dropdown.set_model(Some(&model));
+                        level_dropdown.set_selected(0);
+                        level_dropdown.set_visible(level_stats.len() > 1);
+
+                        result_model.set_result(first_lines, assoc);
+                        event_model.set_events(first_events);
+                        stats_sender.send(Some(level_stats)).expect("Could not send through stats channel");
+                    },
+                    SimulationCommunication::Failure(message) => {
+                        // The previous result stays visible, only the error is shown on top of it.
+                        let toast = Toast::builder()
+                            .title(glib::markup_escape_text(&message))
+                            .priority(ToastPriority::High)
+                            .timeout(0)
+                            .build();
+                        imp.toast_overlay.add_toast(toast.clone());
+                        imp.error_toast.replace(Some(toast));
+                    },
+                }
+                Continue(true)
+            }
+        ));
+
+        stats_receiver.attach(None, clone!(@weak stats_showcase => @default-return Continue(false),
+            move |stats: Option<Vec<(String, CacheDesc, CacheStats)>>| {
+                match stats {
+                    Some(levels) => {
+                        let named = levels.len() > 1;
+                        let describe = |(name, cache, stats): &(String, CacheDesc, CacheStats), long: bool| {
+                            let description = if long { cache.to_string() } else { cache.spec() };
+                            let prefix = if named { format!("{}: ", name) } else { String::new() };
+                            let total = stats.hits() + stats.misses();
+                            format!("{prefix}{4}\nHits: {1}/{0}. Misses: {2}/{0}. Evictions: {3}/{0}", total, stats.hits(), stats.misses(), stats.evictions(), description)
+                        };
+
+                        let label: Vec<String> = levels.iter().map(|level| describe(level, false)).collect();
+                        let tooltip: Vec<String> = levels.iter().map(|level| describe(level, true)).collect();
+                        stats_showcase.set_label(&label.join("\n"));
+                        stats_showcase.set_tooltip_text(Some(&tooltip.join("\n")));
+                        stats_showcase.set_visible(true);
+                    }
+                    None => {
+                        stats_showcase.set_visible(false);
+                    }
+                }
+                Continue(true)
+            }
+        ));
+
+        let container_box = gtk::Box::builder()
+            .orientation(Orientation::Vertical)
+            .vexpand(true)
+            .build();
+
+        container_box.append(&file_display);
+        container_box.append(&separator_top);
+        container_box.append(&result_paned);
+        container_box.append(&separator_bottom);
+        container_box.append(&stats_showcase);
+
+        toast_overlay.set_child(Some(&container_box));
+        self.append(toast_overlay);
+    }
+
+    /// Edits of the trace or configuration in other programs are simulated right away. Editors
+    /// often save by replacing the file, which is reported as created instead of changed.
+    fn watch(&self) {
+        let rewatch = clone!(@weak self as document => move || {
+            let paths = [document.path_buf(), document.config_path()];
+            let monitors: Vec<FileMonitor> = paths
+                .iter()
+                .filter(|path| path.is_file())
+                .filter_map(|path| match gio::File::for_path(path).monitor_file(FileMonitorFlags::NONE, Cancellable::NONE) {
+                    Ok(monitor) => Some(monitor),
+                    Err(err) => {
+                        eprintln!("Could not watch {}: {}", path.display(), err);
+                        None
+                    }
+                })
+                .collect();
+            for monitor in &monitors {
+                monitor.connect_changed(clone!(@weak document => move |_, _, _, event| {
+                    if matches!(event, FileMonitorEvent::ChangesDoneHint | FileMonitorEvent::Created) {
+                        document.simulate();
+                    }
+                }));
+            }
+            // Dropping the old monitors stops them.
+            document.imp().monitors.replace(monitors);
+        });
+        let rewatch = Rc::new(rewatch);
+        self.connect_path_buf_notify(clone!(@strong rewatch => move |_| rewatch()));
+        self.connect_config_path_notify(clone!(@strong rewatch => move |_| rewatch()));
+        rewatch();
+    }
+
+    /// Simulates the trace in the background. If a simulation is already running, the trace is
+    /// simulated again once it is done, so the result always matches the current inputs.
+    pub fn simulate(&self) {
+        let imp = self.imp();
+        let path_buf = self.path_buf();
+        if !path_buf.is_file() {
+            return;
+        }
+        if self.running() {
+            imp.rerun.set(true);
+            return;
+        }
+
+        imp.running.set(true);
+        self.notify_running();
+        if let Some(toast) = imp.error_toast.take() {
+            toast.dismiss();
+        }
+
+        let options = self.sim_options();
+        let sim_sender = imp.sim_sender.get().expect("Documents are built when created").clone();
+        thread::spawn(move || {
+            match run_sim(&path_buf, &options, true) {
+                Ok(result) => {
+                    sim_sender.send(SimulationCommunication::Success(result)).expect("Could not send through channel");
+                },
+                Err(err) => {
+                    let message = format!("Could not simulate {}: {}", path_buf.display(), err);
+                    sim_sender.send(SimulationCommunication::Failure(message)).expect("Could not send through channel");
+                }
+            }
+        });
+    }
+
+    /// The options of the command line with the configuration and overrides of this document.
+    pub fn sim_options(&self) -> SimOptions {
+        let config_path = Some(self.config_path()).filter(|path| !path.as_os_str().is_empty());
+        SimOptions { config_path, ..self.imp().options.borrow().clone() }
+    }
+
+    pub fn overrides(&self) -> CacheOverrides {
+        self.imp().options.borrow().overrides.clone()
+    }
+
+    /// Replaces the overrides of the header bar and simulates the trace with them. The cache
+    /// given on the command line is kept, the fields override it like the trace header.
+    pub fn set_overrides(&self, overrides: CacheOverrides) {
+        {
+            let mut options = self.imp().options.borrow_mut();
+            let cache = options.overrides.cache.take();
+            options.overrides = CacheOverrides { cache, ..overrides };
+        }
+        self.simulate();
+    }
+
+    pub fn add_toast(&self, message: &str) {
+        self.imp().toast_overlay.add_toast(Toast::new(&glib::markup_escape_text(message)));
+    }
+}
+
+/// Builds a column of a result view that shows the text `format` produces for each row, a cache
+/// line or an access.
+fn result_column<T: 'static>(title: &str, expand: bool, format: impl Fn(&T) -> String + 'static) -> ColumnViewColumn {
+    let factory = SignalListItemFactory::new();
+
+    factory.connect_setup(|_, list_item| {
+        let label = Label::builder()
+            .halign(Align::Start)
+            .ellipsize(EllipsizeMode::Start)
+            .build();
+        list_item.downcast_ref::<ListItem>()
+            .expect("Factory items must be ListItems")
+            .set_child(Some(&label));
+    });
+
+    factory.connect_bind(move |_, list_item| {
+        let list_item = list_item.downcast_ref::<ListItem>().expect("Factory items must be ListItems");
+        let row = list_item.item()
+            .and_downcast::<BoxedAnyObject>()
+            .expect("Result model items must be BoxedAnyObjects");
+        let label = list_item.child()
+            .and_downcast::<Label>()
+            .expect("Result cells must be Labels");
+
+        label.set_label(&format(&row.borrow::<T>()));
+    });
+
+    let column = ColumnViewColumn::new(Some(title), Some(factory));
+    column.set_expand(expand);
+    column.set_resizable(true);
+    column
+}
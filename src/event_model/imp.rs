@@ -0,0 +1,48 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gtk::gio;
+use gtk::glib::{self, BoxedAnyObject};
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+use crate::sim::AccessEvent;
+use super::EventRow;
+
+#[derive(Default)]
+pub struct EventModel {
+    pub events: RefCell<Rc<Vec<AccessEvent>>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for EventModel {
+    const NAME: &'static str = "CacheCacheEventModel";
+    type Type = super::EventModel;
+    type Interfaces = (gio::ListModel,);
+}
+
+impl ObjectImpl for EventModel {}
+
+impl ListModelImpl for EventModel {
+    fn item_type(&self) -> glib::Type {
+        BoxedAnyObject::static_type()
+    }
+
+    fn n_items(&self) -> u32 {
+        self.events.borrow().len().try_into().unwrap_or(u32::MAX)
+    }
+
+    fn item(&self, position: u32) -> Option<glib::Object> {
+        // Like the lines of the result model, rows are only created when they are shown.
+        let events = self.events.borrow();
+        if position as usize >= events.len() {
+            return None;
+        }
+
+        let row = EventRow {
+            events: events.clone(),
+            index: position as usize,
+        };
+        Some(BoxedAnyObject::new(row).upcast())
+    }
+}
@@ -0,0 +1,54 @@
+mod imp;
+
+use std::rc::Rc;
+
+use gtk::{glib, gio};
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use glib::Object;
+
+use crate::sim::AccessEvent;
+
+glib::wrapper! {
+    pub struct EventModel(ObjectSubclass<imp::EventModel>)
+        @implements gio::ListModel;
+}
+
+impl EventModel {
+    pub fn new() -> Self {
+        Object::builder().build()
+    }
+
+    /// Replaces the displayed accesses. Each access becomes one row.
+    pub fn set_events(&self, events: Rc<Vec<AccessEvent>>) {
+        let imp = self.imp();
+        let removed = imp.n_items();
+
+        imp.events.replace(events);
+
+        self.items_changed(0, removed, imp.n_items());
+    }
+
+    /// The access shown in row `position`, if there is one.
+    pub fn event(&self, position: u32) -> Option<AccessEvent> {
+        self.imp().events.borrow().get(position as usize).cloned()
+    }
+}
+
+impl Default for EventModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A single access of a simulation result as handed out by `EventModel`.
+pub struct EventRow {
+    events: Rc<Vec<AccessEvent>>,
+    index: usize,
+}
+
+impl EventRow {
+    pub fn event(&self) -> &AccessEvent {
+        &self.events[self.index]
+    }
+}
@@ -0,0 +1,388 @@
+// Randomized cache exercises with their solutions.
+//
+// An exercise is a small cache with a geometry and policy chosen within `Bounds` and a short
+// trace for it. Most accesses go to a single hot set that receives more tags than it has ways, so
+// the trace contains hits, evictions and conflict misses, i.e. misses of lines that were evicted
+// before. The rest are repeated addresses, neighbours in the same line and random addresses.
+// Traces are simulated while they are generated and drawn again until they contain all three.
+//
+// The task asks for the address split, the outcome of every access, the final content of the
+// cache and the statistics. The solution fills in the same tables, see `export::table`.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::export::{self, Blank, OutputFormat};
+use crate::generate::Rng;
+use crate::sim::{CacheDesc, GeometryError, Hierarchy, LevelResult, Strategy};
+
+/// Number of traces that are drawn before giving up on finding one with all kinds of accesses.
+const MAX_ATTEMPTS: usize = 1000;
+
+/// The most lines the cache of an exercise may have. Every line is part of the solution, so larger
+/// caches neither fit on a sheet nor are they worked out by hand.
+pub const MAX_BLOCKS: u64 = 1 << 12;
+
+#[derive(Debug)]
+pub enum ExerciseError {
+    /// A lower bound is larger than the upper one, or a bound allows no cache at all.
+    EmptyBounds(&'static str),
+    /// The bounds allow caches with more than `MAX_BLOCKS` lines.
+    TooLarge,
+    TooFewAccesses { accesses: usize },
+    Geometry(GeometryError),
+    /// No trace with hits, evictions and conflict misses was found, e.g. for a fully associative
+    /// cache with a single set and too few accesses.
+    NotFound,
+}
+
+impl fmt::Display for ExerciseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExerciseError::EmptyBounds(name) => write!(f, "No {} lies within the bounds", name),
+            ExerciseError::TooLarge => write!(
+                f,
+                "The bounds allow caches with more than {} lines, lower the set count or associativity",
+                MAX_BLOCKS
+            ),
+            ExerciseError::TooFewAccesses { accesses } => write!(
+                f,
+                "An exercise needs at least {} accesses, found {}",
+                MIN_ACCESSES, accesses
+            ),
+            ExerciseError::Geometry(err) => write!(f, "Invalid cache geometry: {}", err),
+            ExerciseError::NotFound => write!(
+                f,
+                "Could not find a trace with hits, evictions and conflict misses, allow more accesses"
+            ),
+        }
+    }
+}
+
+impl Error for ExerciseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExerciseError::Geometry(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<GeometryError> for ExerciseError {
+    fn from(err: GeometryError) -> Self {
+        ExerciseError::Geometry(err)
+    }
+}
+
+/// The shortest trace that can contain a miss, an eviction, a conflict miss and a hit.
+pub const MIN_ACCESSES: usize = 4;
+
+/// The caches an exercise may use. Set counts and line sizes are rounded to powers of two.
+#[derive(Clone, Debug)]
+pub struct Bounds {
+    pub addr_size: u64,
+    pub min_sets: u64,
+    pub max_sets: u64,
+    pub min_assoc: u64,
+    pub max_assoc: u64,
+    /// Line sizes in bytes.
+    pub min_line: u64,
+    pub max_line: u64,
+    pub strategies: Vec<Strategy>,
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Bounds {
+            addr_size: 16,
+            min_sets: 2,
+            max_sets: 8,
+            min_assoc: 1,
+            max_assoc: 4,
+            min_line: 8,
+            max_line: 32,
+            strategies: vec![Strategy::LRU, Strategy::LFU],
+        }
+    }
+}
+
+/// Exponents of the powers of two between `min` and `max`.
+fn exponents(min: u64, max: u64, name: &'static str) -> Result<(u64, u64), ExerciseError> {
+    let low = min.max(1).checked_next_power_of_two().ok_or(ExerciseError::EmptyBounds(name))?.trailing_zeros();
+    let high = u64::BITS - 1 - max.max(1).leading_zeros();
+    if min > max || low > high {
+        return Err(ExerciseError::EmptyBounds(name));
+    }
+    Ok((low.into(), high.into()))
+}
+
+/// `n` followed by `word`, with an "s" appended unless `n` is 1.
+fn count(n: u64, word: &str) -> String {
+    format!("{} {}{}", n, word, if n == 1 { "" } else { "s" })
+}
+
+/// A number between `low` and `high`, both included.
+fn between(rng: &mut Rng, low: u64, high: u64) -> u64 {
+    low + rng.below(high - low + 1)
+}
+
+pub struct Exercise {
+    pub seed: u64,
+    pub cache: CacheDesc,
+    pub addrs: Vec<u64>,
+}
+
+impl Exercise {
+    /// Draws an exercise with `accesses` accesses. The same bounds and seed give the same exercise.
+    pub fn generate(bounds: &Bounds, accesses: usize, seed: u64) -> Result<Exercise, ExerciseError> {
+        if accesses < MIN_ACCESSES {
+            return Err(ExerciseError::TooFewAccesses { accesses });
+        }
+        let (min_sets, max_sets) = exponents(bounds.min_sets, bounds.max_sets, "set count")?;
+        let (min_line, max_line) = exponents(bounds.min_line, bounds.max_line, "line size")?;
+        if bounds.min_assoc.max(1) > bounds.max_assoc {
+            return Err(ExerciseError::EmptyBounds("associativity"));
+        }
+        if bounds.strategies.is_empty() {
+            return Err(ExerciseError::EmptyBounds("strategy"));
+        }
+        // Checked once for the largest cache, so the result does not depend on the seed.
+        let max_blocks = bounds.max_assoc.checked_mul(1 << max_sets);
+        if max_blocks.is_none_or(|blocks| blocks > MAX_BLOCKS) {
+            return Err(ExerciseError::TooLarge);
+        }
+
+        let mut rng = Rng::new(seed);
+        let idx_bits = between(&mut rng, min_sets, max_sets);
+        let offset_bits = between(&mut rng, min_line, max_line);
+        let assoc = between(&mut rng, bounds.min_assoc.max(1), bounds.max_assoc);
+        let strategy = bounds.strategies[rng.below(bounds.strategies.len() as u64) as usize];
+        let n_blocks = assoc.checked_mul(1 << idx_bits).expect("The bounds limit the cache to MAX_BLOCKS lines.");
+        let cache = CacheDesc::new(bounds.addr_size, offset_bits, n_blocks, assoc, strategy)?;
+
+        for _ in 0..MAX_ATTEMPTS {
+            let exercise = Exercise {
+                seed,
+                addrs: draw_addrs(&mut rng, &cache, accesses),
+                cache: cache.clone(),
+            };
+            if exercise.is_interesting() {
+                return Ok(exercise);
+            }
+        }
+
+        Err(ExerciseError::NotFound)
+    }
+
+    pub fn simulate(&self) -> LevelResult {
+        let mut hierarchy = Hierarchy::new(std::slice::from_ref(&self.cache), true);
+        for &addr in &self.addrs {
+            hierarchy.access(addr);
+        }
+        let (lines, events, stats) = hierarchy.finish().pop().expect("The hierarchy has a single level.");
+
+        LevelResult {
+            name: "Cache".to_string(),
+            cache: self.cache.clone(),
+            lines,
+            events,
+            stats,
+        }
+    }
+
+    /// Whether the trace contains a hit, an eviction and a conflict miss.
+    fn is_interesting(&self) -> bool {
+        let result = self.simulate();
+        let conflict_miss = result.events.iter().enumerate().any(|(i, event)| {
+            !event.hit
+                && result.events[..i]
+                    .iter()
+                    .any(|earlier| earlier.set == event.set && earlier.tag == event.tag)
+        });
+
+        result.stats.hits() > 0 && result.stats.evictions() > 0 && conflict_miss
+    }
+
+    /// Writes the task, or with `solution` the worked solution, as Markdown or LaTeX.
+    pub fn write(&self, writer: &mut impl Write, format: OutputFormat, solution: bool) -> io::Result<()> {
+        let result = self.simulate();
+        let cache = &self.cache;
+        let line = 1u64 << cache.offset_bits();
+        let addrs: Vec<String> = self.addrs.iter().map(|addr| format!("{:x}", addr)).collect();
+        let split = if solution {
+            format!(
+                "{}, {} and {}",
+                count(cache.tag_bits(), "tag bit"),
+                count(cache.idx_bits(), "set index bit"),
+                count(cache.offset_bits(), "offset bit"),
+            )
+        } else {
+            "___ tag bits, ___ set index bits and ___ offset bits".to_string()
+        };
+        let title = if solution { "Solution" } else { "Exercise" };
+        let description = format!(
+            "A cache with {} of {}, lines of {} bytes and {} replacement is accessed with {}-bit \
+             addresses. The accesses go to the hexadecimal addresses {}.",
+            count(cache.n_sets(), "set"),
+            count(cache.assoc(), "way"),
+            line,
+            cache.strategy(),
+            cache.addr_size(),
+            addrs.join(", "),
+        );
+        let questions = [
+            format!("How are addresses split? {}.", split),
+            "For every access, give the tag, set and offset, whether it hits, the way it uses and the tag it evicts.".to_string(),
+            "Give every entry each cache line held, with the time it entered the line.".to_string(),
+            "How many hits, misses and evictions are there?".to_string(),
+        ];
+
+        match format {
+            OutputFormat::Latex => {
+                writeln!(writer, "\\paragraph{{{} (seed {})}}", title, self.seed)?;
+                writeln!(writer, "{}", description)?;
+                writeln!(writer, "\\begin{{enumerate}}")?;
+                for question in &questions {
+                    writeln!(writer, "\\item {}", question.replace("___", "\\rule{2em}{0.4pt}"))?;
+                }
+                writeln!(writer, "\\end{{enumerate}}")?;
+            }
+            _ => {
+                writeln!(writer, "**{} (seed {})**", title, self.seed)?;
+                writeln!(writer)?;
+                writeln!(writer, "{}", description)?;
+                writeln!(writer)?;
+                for (i, question) in questions.iter().enumerate() {
+                    writeln!(writer, "{}. {}", i + 1, question.replace("___", "\\_\\_\\_"))?;
+                }
+            }
+        }
+        writeln!(writer)?;
+
+        let blanks = if solution { Vec::new() } else { Blank::ALL.to_vec() };
+        export::write(writer, format, &[result], &blanks)
+    }
+}
+
+/// Draws addresses for `cache`, most of them in a single hot set with more tags than ways.
+fn draw_addrs(rng: &mut Rng, cache: &CacheDesc, accesses: usize) -> Vec<u64> {
+    let offset_bits = cache.offset_bits();
+    let idx_bits = cache.idx_bits();
+    // Small tags are easier to work with by hand.
+    let n_tags = 1u64 << cache.tag_bits().min(6);
+    let addr = |tag: u64, set: u64, offset: u64| (tag << (idx_bits + offset_bits)) | (set << offset_bits) | offset;
+    let offset = |rng: &mut Rng| rng.below(1 << offset_bits);
+
+    let hot_set = rng.below(cache.n_sets());
+    let n_hot = (cache.assoc() + 1 + rng.below(2)).min(n_tags);
+    let mut hot_tags: Vec<u64> = Vec::new();
+    while (hot_tags.len() as u64) < n_hot {
+        let tag = rng.below(n_tags);
+        if !hot_tags.contains(&tag) {
+            hot_tags.push(tag);
+        }
+    }
+
+    let mut addrs: Vec<u64> = Vec::with_capacity(accesses);
+    for _ in 0..accesses {
+        let next = match (rng.below(10), addrs.last()) {
+            (0..=4, _) | (_, None) => {
+                let tag = hot_tags[rng.below(n_hot) as usize];
+                addr(tag, hot_set, offset(rng))
+            }
+            // Another byte of the line that was just accessed.
+            (5..=6, Some(&last)) => (last & (u64::MAX << offset_bits)) | offset(rng),
+            (7, _) => addrs[rng.below(addrs.len() as u64) as usize],
+            _ => addr(rng.below(n_tags), rng.below(cache.n_sets()), offset(rng)),
+        };
+        addrs.push(next);
+    }
+
+    addrs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate(bounds: &Bounds, seed: u64) -> Result<Exercise, ExerciseError> {
+        Exercise::generate(bounds, 12, seed)
+    }
+
+    #[test]
+    fn same_seed_same_exercise() {
+        let bounds = Bounds::default();
+        let first = generate(&bounds, 7).unwrap();
+        let second = generate(&bounds, 7).unwrap();
+        assert_eq!(first.cache.spec(), second.cache.spec());
+        assert_eq!(first.addrs, second.addrs);
+
+        let other = generate(&bounds, 8).unwrap();
+        assert!(other.cache.spec() != first.cache.spec() || other.addrs != first.addrs);
+    }
+
+    #[test]
+    fn caches_within_bounds() {
+        let bounds = Bounds {
+            min_sets: 3,
+            max_sets: 5,
+            min_assoc: 2,
+            max_assoc: 3,
+            min_line: 16,
+            max_line: 16,
+            strategies: vec![Strategy::First],
+            ..Bounds::default()
+        };
+        for seed in 0..50 {
+            let exercise = generate(&bounds, seed).unwrap();
+            let cache = &exercise.cache;
+            // Set counts are rounded up to the next power of two.
+            assert_eq!(cache.n_sets(), 4);
+            assert!((2..=3).contains(&cache.assoc()));
+            assert_eq!(cache.offset_bits(), 4);
+            assert_eq!(cache.strategy(), Strategy::First);
+            assert_eq!(exercise.addrs.len(), 12);
+            assert!(exercise.is_interesting());
+        }
+    }
+
+    #[test]
+    fn empty_bounds() {
+        let error = |bounds: Bounds| match generate(&bounds, 0) {
+            Err(ExerciseError::EmptyBounds(name)) => name,
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(_) => panic!("{:?} was accepted", bounds),
+        };
+
+        assert_eq!(error(Bounds { min_sets: 5, max_sets: 4, ..Bounds::default() }), "set count");
+        // No power of two lies between 5 and 7.
+        assert_eq!(error(Bounds { min_sets: 5, max_sets: 7, ..Bounds::default() }), "set count");
+        // The next power of two does not fit into 64 bits.
+        assert_eq!(
+            error(Bounds { min_sets: (1 << 63) + 1, max_sets: u64::MAX, ..Bounds::default() }),
+            "set count"
+        );
+        assert_eq!(error(Bounds { min_line: 64, max_line: 32, ..Bounds::default() }), "line size");
+        assert_eq!(error(Bounds { min_assoc: 3, max_assoc: 2, ..Bounds::default() }), "associativity");
+        assert_eq!(error(Bounds { strategies: Vec::new(), ..Bounds::default() }), "strategy");
+    }
+
+    #[test]
+    fn too_large() {
+        let too_large = |bounds: Bounds| matches!(generate(&bounds, 0), Err(ExerciseError::TooLarge));
+
+        assert!(too_large(Bounds { max_sets: 2048, max_assoc: 4, ..Bounds::default() }));
+        assert!(too_large(Bounds { max_sets: 1 << 63, ..Bounds::default() }));
+        assert!(too_large(Bounds { max_assoc: u64::MAX, ..Bounds::default() }));
+        assert!(!too_large(Bounds { max_sets: 1024, max_assoc: 4, ..Bounds::default() }));
+    }
+
+    #[test]
+    fn too_few_accesses() {
+        assert!(matches!(
+            Exercise::generate(&Bounds::default(), MIN_ACCESSES - 1, 0),
+            Err(ExerciseError::TooFewAccesses { accesses: 3 })
+        ));
+    }
+}
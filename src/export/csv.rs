@@ -0,0 +1,88 @@
+// Simulation results as a single CSV table with a header row. Version 1 of the schema has the
+// columns
+//
+//     level,record,time,address,set,way,tag,offset,hit,evicted,entered,last_used,uses,final,accesses,hits,misses,evictions
+//
+// Every row is one record of a level, named by the `level` column. The `record` column tells
+// which of the other columns are filled, the rest stay empty:
+//
+// - `stats`: `accesses`, `hits`, `misses` and `evictions` of the level. One row per level.
+// - `entry`: an entry that cache line `set`, `way` held with its `tag`, `entered`, `last_used` and
+//   `uses`. `final` is `true` for the final content of the line. Entries are ordered by set, way
+//   and then oldest first.
+// - `access`: an access to `address` at `time`, split into `tag`, `set` and `offset`. `hit` is
+//   `true` or `false`, `way` is the way that hit or was filled, `evicted` the replaced tag if any.
+//   Accesses are in the order the level saw them.
+//
+// Within a level, the stats come first, followed by the entries and the accesses. All numbers are
+// decimal integers, times count the accesses of the level from 0. Without recorded history
+// (`--no-history`), there is only the final entry of every line and no accesses.
+
+use std::io::{self, Write};
+
+use crate::sim::LevelResult;
+
+const HEADER: &str = "level,record,time,address,set,way,tag,offset,hit,evicted,entered,last_used,uses,final,accesses,hits,misses,evictions";
+
+/// Quotes `field` if it contains characters that have a meaning in CSV.
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn write(writer: &mut impl Write, levels: &[LevelResult]) -> io::Result<()> {
+    writeln!(writer, "{}", HEADER)?;
+
+    for LevelResult { name, cache, lines, events, stats } in levels {
+        let name = escape(name);
+
+        writeln!(
+            writer,
+            "{},stats,,,,,,,,,,,,,{},{},{},{}",
+            name,
+            stats.accesses(),
+            stats.hits(),
+            stats.misses(),
+            stats.evictions(),
+        )?;
+
+        for (i, line) in lines.iter().enumerate() {
+            let (set, way) = (i as u64 / cache.assoc(), i as u64 % cache.assoc());
+            for (j, entry) in line.iter().enumerate() {
+                writeln!(
+                    writer,
+                    "{},entry,,,{},{},{},,,,{},{},{},{},,,,",
+                    name,
+                    set,
+                    way,
+                    entry.tag(),
+                    entry.entered(),
+                    entry.last_used(),
+                    entry.count_used(),
+                    j + 1 == line.len(),
+                )?;
+            }
+        }
+
+        for event in events {
+            writeln!(
+                writer,
+                "{},access,{},{},{},{},{},{},{},{},,,,,,,,",
+                name,
+                event.time,
+                event.addr,
+                event.set,
+                event.way,
+                event.tag,
+                event.offset,
+                event.hit,
+                event.evicted.map_or(String::new(), |tag| tag.to_string()),
+            )?;
+        }
+    }
+
+    Ok(())
+}
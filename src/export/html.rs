@@ -0,0 +1,322 @@
+// A self-contained HTML report of a simulation, meant to be handed out to people without the
+// application. Styles and charts are inline, so the file can be opened without any other asset.
+//
+// Every level gets a section with its configuration, a diagram of how addresses are split into
+// tag, set index and offset, its statistics with charts, the history of every line that was used
+// and a table of the accesses. Long traces are cut off after `MAX_ACCESSES` rows and lines after
+// `MAX_ENTRIES` entries to keep the file small enough for a browser.
+
+use std::io::{self, Write};
+
+use crate::sim::{AccessEvent, CacheDesc, CacheStats, LevelResult};
+
+/// Number of accesses listed per level.
+const MAX_ACCESSES: usize = 5000;
+/// Number of entries shown per line, the most recent ones.
+const MAX_ENTRIES: usize = 32;
+/// Number of points of the hit rate chart.
+const MAX_POINTS: usize = 400;
+/// Number of bars of the per-set chart. Consecutive sets share a bar if there are more.
+const MAX_BARS: usize = 64;
+
+const CHART_WIDTH: f64 = 600.0;
+const CHART_HEIGHT: f64 = 160.0;
+
+const HIT_COLOR: &str = "#2ec27e";
+const MISS_COLOR: &str = "#e01b24";
+const EVICTION_COLOR: &str = "#ff7800";
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em auto; max-width: 70em; padding: 0 1em; color: #241f31; }
+h1, h2, h3 { font-weight: 600; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #c0bfbc; padding: 0.2em 0.6em; text-align: right; font-family: monospace; }
+th { background: #f6f5f4; }
+td.hit { background: #c6f0d8; }
+td.miss { background: #f8c8ca; }
+td.eviction { background: #ffd9b3; }
+td.current { font-weight: bold; }
+td.evicted { color: #77767b; }
+p.note { color: #77767b; }
+svg { display: block; margin: 1em 0; }
+svg text { font-family: sans-serif; font-size: 12px; }
+";
+
+/// Escapes text for use in HTML content and attribute values.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Writes a complete HTML document titled `title`, usually the name of the trace.
+pub fn write(writer: &mut impl Write, title: &str, levels: &[LevelResult]) -> io::Result<()> {
+    let title = escape(title);
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, "<html lang=\"en\">")?;
+    writeln!(writer, "<head>")?;
+    writeln!(writer, "<meta charset=\"utf-8\">")?;
+    writeln!(writer, "<title>{} – CacheCache Report</title>", title)?;
+    writeln!(writer, "<style>{}</style>", STYLE)?;
+    writeln!(writer, "</head>")?;
+    writeln!(writer, "<body>")?;
+    writeln!(writer, "<h1>{}</h1>", title)?;
+
+    for level in levels {
+        if levels.len() > 1 {
+            writeln!(writer, "<h2>{}</h2>", escape(&level.name))?;
+        }
+        write_config(writer, &level.cache)?;
+        write_stats(writer, &level.stats, &level.cache, &level.events)?;
+        write_lines(writer, level)?;
+        write_accesses(writer, &level.events)?;
+    }
+
+    writeln!(writer, "</body>")?;
+    writeln!(writer, "</html>")
+}
+
+fn write_config(writer: &mut impl Write, cache: &CacheDesc) -> io::Result<()> {
+    writeln!(writer, "<h3>Configuration</h3>")?;
+    writeln!(writer, "<p>{}</p>", escape(&cache.spec()))?;
+    writeln!(writer, "<table>")?;
+    for (name, value) in [
+        ("Address bits", cache.addr_size().to_string()),
+        ("Block count", cache.n_blocks().to_string()),
+        ("Associativity", cache.assoc().to_string()),
+        ("Sets", cache.n_sets().to_string()),
+        ("Line size", format!("{}B", 1u64 << cache.offset_bits())),
+        ("Policy", cache.strategy().to_string()),
+    ] {
+        writeln!(writer, "<tr><th>{}</th><td>{}</td></tr>", name, value)?;
+    }
+    writeln!(writer, "</table>")?;
+
+    // The address as a bar of its bits, most significant first.
+    writeln!(writer, "<h3>Address Breakdown</h3>")?;
+    let fields = [
+        ("Tag", cache.tag_bits(), "#62a0ea"),
+        ("Set index", cache.idx_bits(), EVICTION_COLOR),
+        ("Offset", cache.offset_bits(), HIT_COLOR),
+    ];
+    let bit_width = CHART_WIDTH / cache.addr_size().max(1) as f64;
+    writeln!(writer, "<svg width=\"{}\" height=\"50\" viewBox=\"0 0 {} 50\">", CHART_WIDTH, CHART_WIDTH)?;
+    let mut x = 0.0;
+    for (name, bits, color) in fields {
+        if bits == 0 {
+            continue;
+        }
+        let width = bits as f64 * bit_width;
+        writeln!(
+            writer,
+            "<rect x=\"{:.1}\" y=\"0\" width=\"{:.1}\" height=\"30\" fill=\"{}\" stroke=\"white\"/>",
+            x, width, color
+        )?;
+        writeln!(
+            writer,
+            "<text x=\"{:.1}\" y=\"45\" text-anchor=\"middle\">{} ({} bits)</text>",
+            x + width / 2.0, name, bits
+        )?;
+        x += width;
+    }
+    writeln!(writer, "</svg>")
+}
+
+fn write_stats(writer: &mut impl Write, stats: &CacheStats, cache: &CacheDesc, events: &[AccessEvent]) -> io::Result<()> {
+    writeln!(writer, "<h3>Statistics</h3>")?;
+    let total = stats.accesses();
+    let percent = |n: u64| if total == 0 { 0.0 } else { n as f64 * 100.0 / total as f64 };
+    writeln!(writer, "<table>")?;
+    writeln!(writer, "<tr><th>Accesses</th><td>{}</td><td></td></tr>", total)?;
+    for (name, n, class) in [
+        ("Hits", stats.hits(), "hit"),
+        ("Misses", stats.misses(), "miss"),
+        ("Evictions", stats.evictions(), "eviction"),
+    ] {
+        writeln!(
+            writer,
+            "<tr><th>{}</th><td class=\"{}\">{}</td><td>{:.1}%</td></tr>",
+            name, class, n, percent(n)
+        )?;
+    }
+    writeln!(writer, "</table>")?;
+
+    // Hits, misses and evictions as horizontal bars relative to all accesses.
+    let rows = [
+        ("Hits", stats.hits(), HIT_COLOR),
+        ("Misses", stats.misses(), MISS_COLOR),
+        ("Evictions", stats.evictions(), EVICTION_COLOR),
+    ];
+    let label_width = 80.0;
+    writeln!(writer, "<svg width=\"{}\" height=\"80\" viewBox=\"0 0 {} 80\">", CHART_WIDTH, CHART_WIDTH)?;
+    for (i, (name, n, color)) in rows.into_iter().enumerate() {
+        let y = i as f64 * 25.0;
+        let width = (CHART_WIDTH - label_width - 60.0) * percent(n) / 100.0;
+        writeln!(writer, "<text x=\"0\" y=\"{:.1}\">{}</text>", y + 15.0, name)?;
+        writeln!(
+            writer,
+            "<rect x=\"{}\" y=\"{:.1}\" width=\"{:.1}\" height=\"20\" fill=\"{}\"/>",
+            label_width, y, width, color
+        )?;
+        writeln!(
+            writer,
+            "<text x=\"{:.1}\" y=\"{:.1}\">{:.1}%</text>",
+            label_width + width + 5.0, y + 15.0, percent(n)
+        )?;
+    }
+    writeln!(writer, "</svg>")?;
+
+    if events.is_empty() {
+        return writeln!(writer, "<p class=\"note\">The accesses were not recorded.</p>");
+    }
+    write_hit_rate_chart(writer, events)?;
+    write_set_chart(writer, cache, events)
+}
+
+/// A line chart of the share of hits among the accesses so far.
+fn write_hit_rate_chart(writer: &mut impl Write, events: &[AccessEvent]) -> io::Result<()> {
+    let step = events.len().div_ceil(MAX_POINTS).max(1);
+    let mut hits = 0;
+    let mut points = Vec::new();
+    for (i, event) in events.iter().enumerate() {
+        hits += u64::from(event.hit);
+        if (i + 1) % step == 0 || i + 1 == events.len() {
+            let x = CHART_WIDTH * (i + 1) as f64 / events.len() as f64;
+            let y = CHART_HEIGHT * (1.0 - hits as f64 / (i + 1) as f64);
+            points.push(format!("{:.1},{:.1}", x, y));
+        }
+    }
+
+    writeln!(writer, "<p>Hit rate over time</p>")?;
+    writeln!(
+        writer,
+        "<svg width=\"{0}\" height=\"{1}\" viewBox=\"-30 -10 {2} {3}\">",
+        CHART_WIDTH + 40.0, CHART_HEIGHT + 30.0, CHART_WIDTH + 40.0, CHART_HEIGHT + 30.0
+    )?;
+    writeln!(
+        writer,
+        "<rect x=\"0\" y=\"0\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"#c0bfbc\"/>",
+        CHART_WIDTH, CHART_HEIGHT
+    )?;
+    writeln!(writer, "<text x=\"-5\" y=\"4\" text-anchor=\"end\">1</text>")?;
+    writeln!(writer, "<text x=\"-5\" y=\"{}\" text-anchor=\"end\">0</text>", CHART_HEIGHT + 4.0)?;
+    writeln!(writer, "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{} accesses</text>", CHART_WIDTH, CHART_HEIGHT + 16.0, events.len())?;
+    writeln!(
+        writer,
+        "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>",
+        points.join(" "), HIT_COLOR
+    )?;
+    writeln!(writer, "</svg>")
+}
+
+/// A stacked bar chart of the hits and misses of every set.
+fn write_set_chart(writer: &mut impl Write, cache: &CacheDesc, events: &[AccessEvent]) -> io::Result<()> {
+    let n_sets = cache.n_sets() as usize;
+    let sets_per_bar = n_sets.div_ceil(MAX_BARS);
+    let n_bars = n_sets.div_ceil(sets_per_bar);
+
+    let mut bars = vec![(0u64, 0u64); n_bars];
+    for event in events {
+        let bar = &mut bars[event.set as usize / sets_per_bar];
+        if event.hit {
+            bar.0 += 1;
+        } else {
+            bar.1 += 1;
+        }
+    }
+    let max = bars.iter().map(|(hits, misses)| hits + misses).max().unwrap_or(0).max(1);
+
+    if sets_per_bar == 1 {
+        writeln!(writer, "<p>Hits and misses per set</p>")?;
+    } else {
+        writeln!(writer, "<p>Hits and misses per {} consecutive sets</p>", sets_per_bar)?;
+    }
+    writeln!(
+        writer,
+        "<svg width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">",
+        CHART_WIDTH, CHART_HEIGHT + 20.0
+    )?;
+    let width = CHART_WIDTH / n_bars as f64;
+    for (i, (hits, misses)) in bars.into_iter().enumerate() {
+        let x = i as f64 * width;
+        let hit_height = CHART_HEIGHT * hits as f64 / max as f64;
+        let miss_height = CHART_HEIGHT * misses as f64 / max as f64;
+        writeln!(
+            writer,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"><title>{} hits</title></rect>",
+            x, CHART_HEIGHT - miss_height - hit_height, width * 0.9, hit_height, HIT_COLOR, hits
+        )?;
+        writeln!(
+            writer,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"><title>{} misses</title></rect>",
+            x, CHART_HEIGHT - miss_height, width * 0.9, miss_height, MISS_COLOR, misses
+        )?;
+    }
+    writeln!(writer, "<text x=\"0\" y=\"{}\">set 0</text>", CHART_HEIGHT + 15.0)?;
+    writeln!(writer, "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">set {}</text>", CHART_WIDTH, CHART_HEIGHT + 15.0, n_sets - 1)?;
+    writeln!(writer, "</svg>")
+}
+
+/// The entries of every line that was used, the final one highlighted.
+fn write_lines(writer: &mut impl Write, level: &LevelResult) -> io::Result<()> {
+    let assoc = level.cache.assoc();
+    writeln!(writer, "<h3>Cache Lines</h3>")?;
+    writeln!(writer, "<p>Tags in hexadecimal with the time they entered the line, oldest first.</p>")?;
+    writeln!(writer, "<table>")?;
+    writeln!(writer, "<tr><th>Set</th><th>Way</th><th colspan=\"{}\">Entries</th></tr>", MAX_ENTRIES + 1)?;
+    for (i, line) in level.lines.iter().enumerate().filter(|(_, line)| !line.is_empty()) {
+        write!(writer, "<tr><td>{}</td><td>{}</td>", i as u64 / assoc, i as u64 % assoc)?;
+        let shown = &line[line.len().saturating_sub(MAX_ENTRIES)..];
+        if shown.len() < line.len() {
+            write!(writer, "<td class=\"evicted\">… {} earlier</td>", line.len() - shown.len())?;
+        }
+        for (j, entry) in shown.iter().enumerate() {
+            let class = if j + 1 == shown.len() { "current" } else { "evicted" };
+            write!(writer, "<td class=\"{}\">{:x} ({})</td>", class, entry.tag(), entry.entered())?;
+        }
+        writeln!(writer, "</tr>")?;
+    }
+    writeln!(writer, "</table>")
+}
+
+/// The accesses with their address breakdown, coloured by their outcome.
+fn write_accesses(writer: &mut impl Write, events: &[AccessEvent]) -> io::Result<()> {
+    if events.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(writer, "<h3>Accesses</h3>")?;
+    writeln!(writer, "<table>")?;
+    writeln!(
+        writer,
+        "<tr><th>Time</th><th>Address</th><th>Tag</th><th>Set</th><th>Offset</th><th>Result</th><th>Way</th><th>Evicted</th></tr>"
+    )?;
+    for event in events.iter().take(MAX_ACCESSES) {
+        let class = match (event.hit, event.evicted) {
+            (true, _) => "hit",
+            (false, None) => "miss",
+            (false, Some(_)) => "eviction",
+        };
+        writeln!(
+            writer,
+            "<tr><td>{}</td><td>{:x}</td><td>{:x}</td><td>{}</td><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td class=\"{}\">{}</td></tr>",
+            event.time,
+            event.addr,
+            event.tag,
+            event.set,
+            event.offset,
+            class,
+            if event.hit { "hit" } else { "miss" },
+            event.way,
+            if event.evicted.is_some() { "eviction" } else { "" },
+            event.evicted.map_or("-".to_string(), |tag| format!("{:x}", tag)),
+        )?;
+    }
+    writeln!(writer, "</table>")?;
+    if events.len() > MAX_ACCESSES {
+        writeln!(writer, "<p class=\"note\">{} more accesses are not listed.</p>", events.len() - MAX_ACCESSES)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,197 @@
+// Simulation results as a single JSON object. Version 1 of the schema:
+//
+//     {
+//       "version": 1,
+//       "levels": [
+//         {
+//           "name": "Cache",
+//           "cache": {
+//             "spec": "64B, 16B line, fully-associative, LRU, 32-bit",
+//             "address_bits": 32, "offset_bits": 4, "index_bits": 0, "tag_bits": 28,
+//             "blocks": 4, "sets": 1, "associativity": 4, "policy": "LRU"
+//           },
+//           "stats": { "accesses": 5, "hits": 1, "misses": 4, "evictions": 0 },
+//           "lines": [
+//             {
+//               "set": 0, "way": 0,
+//               "entry": { "tag": 1, "entered": 0, "last_used": 3, "uses": 2 },
+//               "history": [ { "tag": 1, "entered": 0, "last_used": 3, "uses": 2 } ]
+//             }
+//           ],
+//           "accesses": [
+//             {
+//               "time": 0, "address": 16, "tag": 1, "set": 0, "offset": 0,
+//               "hit": false, "way": 0, "evicted": null
+//             }
+//           ]
+//         }
+//       ]
+//     }
+//
+// `levels` are ordered first level first, `lines` by set and then way. `entry` is the final
+// content of the line or null if it stayed empty, `history` every entry the line held, oldest
+// first, including the final one. `accesses` are the accesses the level saw in order, `time`
+// counts them from 0 and is the clock of `entered` and `last_used`. `way` is the way that hit or
+// was filled, `evicted` the tag that was replaced or null. Without recorded history
+// (`--no-history`), `history` only contains the final entry and `accesses` is empty.
+//
+// All numbers are integers. Addresses and tags may exceed 2^53, so consumers in languages
+// without 64-bit integers should read them with care.
+
+use std::io::{self, Write};
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use crate::sim::{AccessEvent, CacheEntry, LevelResult};
+
+/// The newest version of the schema.
+pub const VERSION: u32 = 1;
+
+struct Document<'a>(&'a [LevelResult]);
+
+impl Serialize for Document<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut document = serializer.serialize_struct("Document", 2)?;
+        document.serialize_field("version", &VERSION)?;
+        document.serialize_field("levels", &Levels(self.0))?;
+        document.end()
+    }
+}
+
+struct Levels<'a>(&'a [LevelResult]);
+
+impl Serialize for Levels<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(Level))
+    }
+}
+
+struct Level<'a>(&'a LevelResult);
+
+impl Serialize for Level<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let LevelResult { name, cache, stats, .. } = self.0;
+
+        let mut level = serializer.serialize_struct("Level", 5)?;
+        level.serialize_field("name", name)?;
+        level.serialize_field("cache", &Cache {
+            spec: cache.spec(),
+            address_bits: cache.addr_size(),
+            offset_bits: cache.offset_bits(),
+            index_bits: cache.idx_bits(),
+            tag_bits: cache.tag_bits(),
+            blocks: cache.n_blocks(),
+            sets: cache.n_sets(),
+            associativity: cache.assoc(),
+            policy: cache.strategy().to_string(),
+        })?;
+        level.serialize_field("stats", &Stats {
+            accesses: stats.accesses(),
+            hits: stats.hits(),
+            misses: stats.misses(),
+            evictions: stats.evictions(),
+        })?;
+        level.serialize_field("lines", &Lines(self.0))?;
+        level.serialize_field("accesses", &Accesses(&self.0.events))?;
+        level.end()
+    }
+}
+
+#[derive(serde::Serialize)]
+struct Cache {
+    spec: String,
+    address_bits: u64,
+    offset_bits: u64,
+    index_bits: u64,
+    tag_bits: u64,
+    blocks: u64,
+    sets: u64,
+    associativity: u64,
+    policy: String,
+}
+
+#[derive(serde::Serialize)]
+struct Stats {
+    accesses: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+struct Lines<'a>(&'a LevelResult);
+
+impl Serialize for Lines<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let assoc = self.0.cache.assoc();
+        serializer.collect_seq(self.0.lines.iter().enumerate().map(|(i, history)| Line {
+            set: i as u64 / assoc,
+            way: i as u64 % assoc,
+            entry: history.last().map(Entry),
+            history: Entries(history),
+        }))
+    }
+}
+
+#[derive(serde::Serialize)]
+struct Line<'a> {
+    set: u64,
+    way: u64,
+    entry: Option<Entry<'a>>,
+    history: Entries<'a>,
+}
+
+struct Entries<'a>(&'a [CacheEntry]);
+
+impl Serialize for Entries<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(Entry))
+    }
+}
+
+struct Entry<'a>(&'a CacheEntry);
+
+impl Serialize for Entry<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut entry = serializer.serialize_struct("Entry", 4)?;
+        entry.serialize_field("tag", &self.0.tag())?;
+        entry.serialize_field("entered", &self.0.entered())?;
+        entry.serialize_field("last_used", &self.0.last_used())?;
+        entry.serialize_field("uses", &self.0.count_used())?;
+        entry.end()
+    }
+}
+
+struct Accesses<'a>(&'a [AccessEvent]);
+
+impl Serialize for Accesses<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(|event| Access {
+            time: event.time,
+            address: event.addr,
+            tag: event.tag,
+            set: event.set,
+            offset: event.offset,
+            hit: event.hit,
+            way: event.way,
+            evicted: event.evicted,
+        }))
+    }
+}
+
+#[derive(serde::Serialize)]
+struct Access {
+    time: u64,
+    address: u64,
+    tag: u64,
+    set: u64,
+    offset: u64,
+    hit: bool,
+    way: u64,
+    evicted: Option<u64>,
+}
+
+/// Writes the levels as a single JSON object followed by a newline.
+pub fn write(writer: &mut impl Write, levels: &[LevelResult]) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, &Document(levels))?;
+    writeln!(writer)
+}
@@ -0,0 +1,67 @@
+// The tables of `table` as LaTeX `tabular` environments that can be pasted into exercise sheets.
+// Values are set in `\texttt`, blank cells are as wide as the widest value of their column.
+
+use std::io::{self, Write};
+
+use crate::sim::LevelResult;
+
+use super::table::{self, Blank, Table};
+
+/// Escapes the characters that have a meaning in LaTeX.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn write_table(writer: &mut impl Write, table: &Table) -> io::Result<()> {
+    let widths = table.widths();
+    let columns: String = table.numeric.iter().map(|&numeric| if numeric { "r|" } else { "l|" }).collect();
+
+    writeln!(writer, "\\begin{{tabular}}{{|{}}}", columns)?;
+    writeln!(writer, "\\hline")?;
+    let header: Vec<String> = table.header.iter().map(|title| escape(title)).collect();
+    writeln!(writer, "{} \\\\", header.join(" & "))?;
+    writeln!(writer, "\\hline")?;
+    for row in &table.rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| match (cell.blank, cell.text.is_empty()) {
+                (true, _) => format!("\\hphantom{{\\texttt{{{}}}}}", "0".repeat(width)),
+                (false, true) => String::new(),
+                (false, false) => format!("\\texttt{{{}}}", escape(&cell.text)),
+            })
+            .collect();
+        writeln!(writer, "{} \\\\", cells.join(" & "))?;
+        writeln!(writer, "\\hline")?;
+    }
+    writeln!(writer, "\\end{{tabular}}")
+}
+
+pub fn write(writer: &mut impl Write, levels: &[LevelResult], blanks: &[Blank]) -> io::Result<()> {
+    for (i, level) in levels.iter().enumerate() {
+        if i > 0 {
+            writeln!(writer)?;
+        }
+        writeln!(writer, "% {}: {}", level.name, level.cache.spec())?;
+        write_table(writer, &table::lines(level, blanks))?;
+        writeln!(writer)?;
+        write_table(writer, &table::accesses(level, blanks))?;
+        writeln!(writer)?;
+        write_table(writer, &table::stats(level, blanks))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+// The tables of `table` as Markdown pipe tables. Blank cells are lines of underscores as wide as
+// the widest value of their column.
+
+use std::io::{self, Write};
+
+use crate::sim::LevelResult;
+
+use super::table::{self, Blank, Table};
+
+/// Escapes the characters that would end a cell or start emphasis.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '|' | '\\' | '*' | '_' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn write_table(writer: &mut impl Write, table: &Table) -> io::Result<()> {
+    let widths = table.widths();
+
+    let header: Vec<String> = table.header.iter().map(|title| escape(title)).collect();
+    writeln!(writer, "| {} |", header.join(" | "))?;
+    let alignment: Vec<&str> = table.numeric.iter().map(|&numeric| if numeric { "--:" } else { ":--" }).collect();
+    writeln!(writer, "|{}|", alignment.join("|"))?;
+
+    for row in &table.rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| match (cell.blank, cell.text.is_empty()) {
+                (true, _) => "\\_".repeat(width),
+                (false, true) => String::new(),
+                (false, false) => escape(&cell.text),
+            })
+            .collect();
+        writeln!(writer, "| {} |", cells.join(" | "))?;
+    }
+
+    Ok(())
+}
+
+pub fn write(writer: &mut impl Write, levels: &[LevelResult], blanks: &[Blank]) -> io::Result<()> {
+    for (i, level) in levels.iter().enumerate() {
+        if i > 0 {
+            writeln!(writer)?;
+        }
+        writeln!(writer, "**{}**: {}", escape(&level.name), escape(&level.cache.spec()))?;
+        writeln!(writer)?;
+        write_table(writer, &table::lines(level, blanks))?;
+        writeln!(writer)?;
+        write_table(writer, &table::accesses(level, blanks))?;
+        writeln!(writer)?;
+        write_table(writer, &table::stats(level, blanks))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+// Simulation results in formats meant for other programs and documents. The schemas of the JSON
+// and CSV output are documented at the top of their modules and only change together with their
+// version. LaTeX and Markdown contain the tables of exercise sheets, see `table`.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::sim::LevelResult;
+
+mod csv;
+mod html;
+mod json;
+mod latex;
+mod markdown;
+mod table;
+mod text;
+
+pub use table::Blank;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The cache lines and a summary for people to read.
+    Text,
+    Json,
+    Csv,
+    /// Tables of the lines and accesses, which can leave cells blank for worksheets.
+    Latex,
+    Markdown,
+}
+
+impl OutputFormat {
+    /// Whether the format consists of the tables that `Blank` applies to.
+    pub fn has_tables(&self) -> bool {
+        matches!(self, OutputFormat::Latex | OutputFormat::Markdown)
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = ParseOutputFormatError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "latex" => Ok(OutputFormat::Latex),
+            "markdown" => Ok(OutputFormat::Markdown),
+            _ => Err(ParseOutputFormatError),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseOutputFormatError;
+impl fmt::Display for ParseOutputFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid output format, expected text, json, csv, latex or markdown")
+    }
+}
+
+impl Error for ParseOutputFormatError {}
+
+/// Writes the simulated levels, first level first. The cells of the columns in `blanks` are left
+/// empty in formats with tables, the other formats ignore them.
+pub fn write(writer: &mut impl Write, format: OutputFormat, levels: &[LevelResult], blanks: &[Blank]) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => text::write(writer, levels)?,
+        OutputFormat::Json => json::write(writer, levels)?,
+        OutputFormat::Csv => csv::write(writer, levels)?,
+        OutputFormat::Latex => latex::write(writer, levels, blanks)?,
+        OutputFormat::Markdown => markdown::write(writer, levels, blanks)?,
+    }
+
+    writer.flush()
+}
+
+/// Writes a table of every access of every level, see `sim::AccessEvent`.
+pub fn write_events(writer: &mut impl Write, levels: &[LevelResult]) -> io::Result<()> {
+    text::write_events(writer, levels)?;
+    writer.flush()
+}
+
+/// Writes a self-contained HTML report of the simulated levels, see `html`.
+pub fn write_report(writer: &mut impl Write, title: &str, levels: &[LevelResult]) -> io::Result<()> {
+    html::write(writer, title, levels)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::slice;
+
+    use crate::sim::{self, Hierarchy};
+
+    use super::*;
+
+    /// Simulates `test/evict` like `simulate` does.
+    fn evict() -> Vec<LevelResult> {
+        let (cache, addrs) = sim::read(include_str!("../../test/evict").as_bytes(), true).unwrap();
+        let cache = cache.unwrap();
+
+        let mut hierarchy = Hierarchy::new(slice::from_ref(&cache), true);
+        for addr in addrs {
+            hierarchy.access(addr.unwrap());
+        }
+        let (lines, events, stats) = hierarchy.finish().pop().unwrap();
+        vec![LevelResult { name: "Cache".to_string(), cache, lines, events, stats }]
+    }
+
+    fn output(format: OutputFormat) -> String {
+        let mut bytes = Vec::new();
+        write(&mut bytes, format, &evict(), &[]).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn json_snapshot() {
+        assert_eq!(output(OutputFormat::Json), include_str!("../../test/evict.json"));
+    }
+
+    #[test]
+    fn csv_snapshot() {
+        assert_eq!(output(OutputFormat::Csv), include_str!("../../test/evict.csv"));
+    }
+}
@@ -0,0 +1,191 @@
+// The tables of exercise sheets: the entries of every cache line, the accesses with their
+// address breakdown and the statistics. They are built once and rendered as LaTeX or Markdown.
+// Cells of the columns chosen with `Blank` are left empty, so the same command with and without
+// blanks produces a worksheet and its solution with the same layout.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::sim::LevelResult;
+
+/// Columns that can be left blank for students to fill in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blank {
+    Tag,
+    Set,
+    Offset,
+    Result,
+    Way,
+    Evicted,
+    /// The entries of the cache lines.
+    Lines,
+    /// The hits, misses and evictions.
+    Stats,
+}
+
+impl Blank {
+    pub const ALL: [Blank; 8] = [
+        Blank::Tag,
+        Blank::Set,
+        Blank::Offset,
+        Blank::Result,
+        Blank::Way,
+        Blank::Evicted,
+        Blank::Lines,
+        Blank::Stats,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Blank::Tag => "tag",
+            Blank::Set => "set",
+            Blank::Offset => "offset",
+            Blank::Result => "result",
+            Blank::Way => "way",
+            Blank::Evicted => "evicted",
+            Blank::Lines => "lines",
+            Blank::Stats => "stats",
+        }
+    }
+
+    /// Parses a comma separated list of column names, or `all` for every column.
+    pub fn parse_list(list: &str) -> Result<Vec<Blank>, ParseBlankError> {
+        if list.trim() == "all" {
+            return Ok(Blank::ALL.to_vec());
+        }
+        list.split(',').map(|name| name.trim().parse()).collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseBlankError;
+impl fmt::Display for ParseBlankError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = Blank::ALL.iter().map(Blank::name).collect();
+        write!(f, "Invalid column to blank, expected all or a list of {}", names.join(", "))
+    }
+}
+
+impl Error for ParseBlankError {}
+
+impl FromStr for Blank {
+    type Err = ParseBlankError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Blank::ALL
+            .into_iter()
+            .find(|blank| blank.name().eq_ignore_ascii_case(s))
+            .ok_or(ParseBlankError)
+    }
+}
+
+pub struct Cell {
+    pub text: String,
+    /// Whether the cell is left empty on the worksheet.
+    pub blank: bool,
+}
+
+pub struct Table {
+    pub header: Vec<String>,
+    /// Whether the columns are numbers, which are aligned to the right.
+    pub numeric: Vec<bool>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    /// The widest text of every column, so blanks can be as wide as the answers.
+    pub fn widths(&self) -> Vec<usize> {
+        let mut widths: Vec<usize> = self.header.iter().map(|title| title.chars().count()).collect();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.text.chars().count());
+            }
+        }
+        widths
+    }
+}
+
+/// A row for every cache line with its entries oldest first. Lines are listed even if they stayed
+/// empty, so a worksheet does not tell which lines were used.
+pub fn lines(level: &LevelResult, blanks: &[Blank]) -> Table {
+    let assoc = level.cache.assoc();
+    let n_entries = level.lines.iter().map(Vec::len).max().unwrap_or(0);
+    let blank = blanks.contains(&Blank::Lines);
+
+    let mut header = vec!["Set".to_string(), "Way".to_string()];
+    header.extend((1..=n_entries).map(|i| format!("Entry {}", i)));
+    let mut numeric = vec![true, true];
+    numeric.resize(header.len(), false);
+
+    let rows = level
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let mut row = vec![
+                Cell { text: (i as u64 / assoc).to_string(), blank: false },
+                Cell { text: (i as u64 % assoc).to_string(), blank: false },
+            ];
+            // Lines that held fewer entries have empty cells at the end, which are blank as well.
+            row.extend((0..n_entries).map(|j| Cell {
+                text: line.get(j).map_or(String::new(), |entry| format!("{:x} ({})", entry.tag(), entry.entered())),
+                blank,
+            }));
+            row
+        })
+        .collect();
+
+    Table { header, numeric, rows }
+}
+
+/// A row for every access with the address split into tag, set and offset and its outcome.
+pub fn accesses(level: &LevelResult, blanks: &[Blank]) -> Table {
+    let header = ["Time", "Address", "Tag", "Set", "Offset", "Result", "Way", "Evicted"];
+    let blank = |column| blanks.contains(&column);
+
+    let rows = level
+        .events
+        .iter()
+        .map(|event| {
+            vec![
+                Cell { text: event.time.to_string(), blank: false },
+                Cell { text: format!("{:x}", event.addr), blank: false },
+                Cell { text: format!("{:x}", event.tag), blank: blank(Blank::Tag) },
+                Cell { text: event.set.to_string(), blank: blank(Blank::Set) },
+                Cell { text: event.offset.to_string(), blank: blank(Blank::Offset) },
+                Cell {
+                    text: if event.hit { "hit" } else { "miss" }.to_string(),
+                    blank: blank(Blank::Result),
+                },
+                Cell { text: event.way.to_string(), blank: blank(Blank::Way) },
+                Cell {
+                    text: event.evicted.map_or("-".to_string(), |tag| format!("{:x}", tag)),
+                    blank: blank(Blank::Evicted),
+                },
+            ]
+        })
+        .collect();
+
+    Table {
+        header: header.map(String::from).to_vec(),
+        numeric: vec![true, true, true, true, true, false, true, true],
+        rows,
+    }
+}
+
+/// A single row with the number of accesses, hits, misses and evictions.
+pub fn stats(level: &LevelResult, blanks: &[Blank]) -> Table {
+    let blank = blanks.contains(&Blank::Stats);
+    let stats = &level.stats;
+
+    Table {
+        header: ["Accesses", "Hits", "Misses", "Evictions"].map(String::from).to_vec(),
+        numeric: vec![true; 4],
+        rows: vec![vec![
+            Cell { text: stats.accesses().to_string(), blank: false },
+            Cell { text: stats.hits().to_string(), blank },
+            Cell { text: stats.misses().to_string(), blank },
+            Cell { text: stats.evictions().to_string(), blank },
+        ]],
+    }
+}
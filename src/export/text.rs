@@ -0,0 +1,70 @@
+// The cache lines and a summary of every level, as printed by `--no-window`.
+
+use std::io::{self, Write};
+
+use crate::sim::{self, LevelResult};
+
+pub fn write(writer: &mut impl Write, levels: &[LevelResult]) -> io::Result<()> {
+    for LevelResult { name, cache, lines, stats, .. } in levels {
+        if levels.len() > 1 {
+            writeln!(writer, "{}:", name)?;
+        }
+
+        for (i, line) in lines.iter().enumerate() {
+            writeln!(writer, "{}", sim::format_cache_line(line, i as u64 / cache.assoc()))?;
+        }
+
+        writeln!(writer, "Cache: {}", cache)?;
+        writeln!(writer, "Hits: {1}/{0}. Misses: {2}/{0}. Evictions: {3}/{0}. Tag Bits: {4}, Index Bits: {5} Offset Bits: {6}",
+            stats.accesses(),
+            stats.hits(),
+            stats.misses(),
+            stats.evictions(),
+            cache.tag_bits(),
+            cache.idx_bits(),
+            cache.offset_bits(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes a table with a row for every access of every level.
+pub fn write_events(writer: &mut impl Write, levels: &[LevelResult]) -> io::Result<()> {
+    for LevelResult { name, cache, events, .. } in levels {
+        if levels.len() > 1 {
+            writeln!(writer, "{}:", name)?;
+        }
+
+        let digits = |n: u64| n.to_string().len();
+        let hex_digits = |bits: u64| bits.div_ceil(4).max(1) as usize;
+        let time = digits(events.len().saturating_sub(1) as u64).max("Time".len());
+        let addr = hex_digits(cache.addr_size()).max("Address".len());
+        let tag = hex_digits(cache.tag_bits()).max("Tag".len());
+        let set = digits(cache.n_sets() - 1).max("Set".len());
+        let offset = digits((1 << cache.offset_bits()) - 1).max("Offset".len());
+        let way = digits(cache.assoc() - 1).max("Way".len());
+
+        writeln!(
+            writer,
+            "{:>time$}  {:>addr$}  {:>tag$}  {:>set$}  {:>offset$}  Result  {:>way$}  Evicted",
+            "Time", "Address", "Tag", "Set", "Offset", "Way",
+        )?;
+        for event in events {
+            writeln!(
+                writer,
+                "{:>time$}  {:>addr$x}  {:>tag$x}  {:>set$}  {:>offset$}  {:<6}  {:>way$}  {}",
+                event.time,
+                event.addr,
+                event.tag,
+                event.set,
+                event.offset,
+                if event.hit { "hit" } else { "miss" },
+                event.way,
+                event.evicted.map_or("-".to_string(), |tag| format!("{:x}", tag)),
+            )?;
+        }
+    }
+
+    Ok(())
+}
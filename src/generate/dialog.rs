@@ -0,0 +1,245 @@
+// A dialog to generate synthetic traces from the GUI.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread;
+
+use gtk::gio::Cancellable;
+use gtk::glib::{self, clone, MainContext, Priority};
+use gtk::{prelude::*, Align, Button, DialogError, DropDown, Entry, FileDialog, Grid, Label, Orientation, Window};
+use libadwaita::HeaderBar;
+
+use crate::sim::{self, CacheDesc};
+
+use super::{parse_address, Pattern, Workload};
+
+/// The entries of the dialog, one per parameter.
+struct Form {
+    pattern: DropDown,
+    base: Entry,
+    element_size: Entry,
+    count: Entry,
+    stride: Entry,
+    block: Entry,
+    accesses: Entry,
+    exponent: Entry,
+    seed: Entry,
+    cache: Entry,
+}
+
+fn parse_entry<T: FromStr>(entry: &Entry, name: &str) -> Result<T, String> {
+    entry
+        .text()
+        .trim()
+        .parse()
+        .map_err(|_| format!("{} must be a number", name))
+}
+
+impl Form {
+    fn pattern(&self) -> Pattern {
+        Pattern::ALL[self.pattern.selected() as usize]
+    }
+
+    /// Only the parameters the selected pattern uses can be edited.
+    fn update_sensitivity(&self) {
+        let pattern = self.pattern();
+        self.stride.set_sensitive(pattern == Pattern::Strided);
+        self.block.set_sensitive(pattern == Pattern::BlockedMatmul);
+        self.exponent.set_sensitive(pattern == Pattern::Zipf);
+        self.accesses.set_sensitive(pattern.is_random());
+        self.seed.set_sensitive(pattern.is_random());
+    }
+
+    fn workload(&self) -> Result<(Workload, Option<CacheDesc>), String> {
+        let accesses = match self.accesses.text().trim() {
+            "" => None,
+            _ => Some(parse_entry(&self.accesses, "Accesses")?),
+        };
+        let cache = match self.cache.text().trim() {
+            "" => None,
+            spec => Some(CacheDesc::from_spec(spec).map_err(|err| format!("Cache: {}", err))?),
+        };
+
+        let workload = Workload {
+            pattern: self.pattern(),
+            base: parse_address(&self.base.text())
+                .ok_or("Base address must be a number like 0x1000")?,
+            element_size: sim::parse_size(&self.element_size.text())
+                .map_err(|err| format!("Element size: {}", err))?,
+            count: parse_entry(&self.count, "Count")?,
+            stride: parse_entry(&self.stride, "Stride")?,
+            block: parse_entry(&self.block, "Block")?,
+            accesses,
+            exponent: parse_entry(&self.exponent, "Zipf exponent")?,
+            seed: parse_entry(&self.seed, "Seed")?,
+        };
+        workload.check().map_err(|err| err.to_string())?;
+
+        Ok((workload, cache))
+    }
+}
+
+/// Shows the dialog. `on_generated` is called with the path of every written trace.
+pub fn show(parent: &impl IsA<Window>, on_generated: impl Fn(PathBuf) + 'static) {
+    let defaults = Workload::default();
+    let entry = |text: &str, tooltip: &str| {
+        Entry::builder()
+            .text(text)
+            .tooltip_text(tooltip)
+            .hexpand(true)
+            .build()
+    };
+
+    let names: Vec<&str> = Pattern::ALL.iter().map(Pattern::name).collect();
+    let form = Form {
+        pattern: DropDown::from_strings(&names),
+        base: entry(&format!("{:#x}", defaults.base), "Address of the first element"),
+        element_size: entry(&format!("{}B", defaults.element_size), "Size of an element, e.g. 8B"),
+        count: entry(
+            &defaults.count.to_string(),
+            "Number of elements, or rows and columns of the matrices",
+        ),
+        stride: entry(&defaults.stride.to_string(), "Distance of the accesses in elements"),
+        block: entry(&defaults.block.to_string(), "Rows and columns of the blocks"),
+        accesses: entry("", "Number of accesses, the element count if empty"),
+        exponent: entry(&defaults.exponent.to_string(), "Larger exponents make the first elements hotter"),
+        seed: entry(&defaults.seed.to_string(), "Traces with the same seed are equal"),
+        cache: entry("", "Cache described in the header, e.g. 4KiB, 64B line, 4-way. No header if empty"),
+    };
+
+    let grid = Grid::builder()
+        .row_spacing(10)
+        .column_spacing(10)
+        .margin_top(10)
+        .margin_end(10)
+        .margin_start(10)
+        .margin_bottom(10)
+        .build();
+
+    let rows: [(&str, &gtk::Widget); 10] = [
+        ("Pattern", form.pattern.upcast_ref()),
+        ("Base Address", form.base.upcast_ref()),
+        ("Element Size", form.element_size.upcast_ref()),
+        ("Count", form.count.upcast_ref()),
+        ("Stride", form.stride.upcast_ref()),
+        ("Block", form.block.upcast_ref()),
+        ("Accesses", form.accesses.upcast_ref()),
+        ("Zipf Exponent", form.exponent.upcast_ref()),
+        ("Seed", form.seed.upcast_ref()),
+        ("Cache", form.cache.upcast_ref()),
+    ];
+    for (row, (title, widget)) in rows.into_iter().enumerate() {
+        let label = Label::builder().label(title).halign(Align::Start).build();
+        grid.attach(&label, 0, row as i32, 1, 1);
+        grid.attach(widget, 1, row as i32, 1, 1);
+    }
+
+    let error_label = Label::builder()
+        .wrap(true)
+        .visible(false)
+        .css_classes(["error"])
+        .build();
+
+    let container_box = gtk::Box::new(Orientation::Vertical, 0);
+    container_box.append(&grid);
+    container_box.append(&error_label);
+
+    let generate_button = Button::builder()
+        .label("Generate")
+        .css_classes(["suggested-action"])
+        .build();
+    let header_bar = HeaderBar::new();
+    header_bar.pack_end(&generate_button);
+
+    let dialog = Window::builder()
+        .title("Generate Trace")
+        .transient_for(parent)
+        .modal(true)
+        .default_width(400)
+        .child(&container_box)
+        .titlebar(&header_bar)
+        .build();
+
+    form.update_sensitivity();
+    let form = std::rc::Rc::new(form);
+    form.pattern.connect_selected_notify(clone!(@weak form => move |_| form.update_sensitivity()));
+
+    let (sender, receiver) = MainContext::channel(Priority::default());
+    // The dialog may be closed while a trace is written, which is opened nevertheless.
+    receiver.attach(None, clone!(@weak-allow-none dialog, @weak-allow-none error_label, @weak-allow-none generate_button =>
+        move |result: Result<PathBuf, String>| {
+            match result {
+                Ok(path) => {
+                    on_generated(path);
+                    if let Some(dialog) = &dialog {
+                        dialog.close();
+                    }
+                }
+                Err(message) => {
+                    if let (Some(error_label), Some(generate_button)) = (&error_label, &generate_button) {
+                        error_label.set_label(&message);
+                        error_label.set_visible(true);
+                        generate_button.set_sensitive(true);
+                    }
+                }
+            }
+            Continue(true)
+        }
+    ));
+
+    generate_button.connect_clicked(clone!(@weak dialog, @weak error_label, @strong form => move |button| {
+        let (workload, cache) = match form.workload() {
+            Ok(parameters) => parameters,
+            Err(message) => {
+                error_label.set_label(&message);
+                error_label.set_visible(true);
+                return;
+            }
+        };
+        error_label.set_visible(false);
+        button.set_sensitive(false);
+
+        let file_dialogue = FileDialog::builder()
+            .title("Save Trace")
+            .initial_name(workload.pattern.name())
+            .build();
+        let sender = sender.clone();
+        file_dialogue.save(Some(&dialog), Cancellable::NONE, clone!(@weak button => move |result| {
+            let path = match result.map(|file| file.path()) {
+                Ok(Some(path)) => path,
+                Ok(None) => {
+                    let message = "Traces can only be saved to local files".to_string();
+                    sender.send(Err(message)).expect("Could not send through channel");
+                    return;
+                }
+                Err(err) => {
+                    if err.kind::<DialogError>() != Some(DialogError::Dismissed) {
+                        sender.send(Err(err.message().to_string())).expect("Could not send through channel");
+                    } else {
+                        button.set_sensitive(true);
+                    }
+                    return;
+                }
+            };
+
+            // Large traces take a while, so they are written without blocking the window.
+            thread::spawn(move || {
+                let write = || -> Result<(), Box<dyn Error>> {
+                    let addrs = workload.addrs()?;
+                    let mut writer = BufWriter::new(File::create(&path)?);
+                    Ok(sim::write(&mut writer, cache.as_ref(), addrs)?)
+                };
+                let result = match write() {
+                    Ok(()) => Ok(path),
+                    Err(err) => Err(format!("Could not write {}: {}", path.display(), err)),
+                };
+                sender.send(result).expect("Could not send through channel");
+            });
+        }));
+    }));
+
+    dialog.present();
+}
@@ -0,0 +1,390 @@
+// Synthetic traces of common access patterns, e.g. for exercises.
+//
+// Every pattern accesses elements of `element_size` bytes starting at `base`. The matrix patterns
+// use square matrices with `count` rows and columns, stored row by row. The blocked matrix
+// multiplication computes C = A * B with A, B and C stored one after another, reading A[i][k],
+// B[k][j] and C[i][j] in its innermost loop.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+pub mod dialog;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// Every element once, in order.
+    Sequential,
+    /// Every `stride`-th element, then the ones after them, until every element was accessed once.
+    Strided,
+    /// Uniformly random elements.
+    Random,
+    /// Random elements where the first ones are much more likely, following Zipf's law.
+    Zipf,
+    /// A matrix traversed row by row.
+    RowMajor,
+    /// A matrix traversed column by column.
+    ColumnMajor,
+    /// Matrix multiplication in blocks of `block` rows and columns.
+    BlockedMatmul,
+    /// A linked list whose nodes are randomly placed in the array.
+    PointerChase,
+}
+
+impl Pattern {
+    pub const ALL: [Pattern; 8] = [
+        Pattern::Sequential,
+        Pattern::Strided,
+        Pattern::Random,
+        Pattern::Zipf,
+        Pattern::RowMajor,
+        Pattern::ColumnMajor,
+        Pattern::BlockedMatmul,
+        Pattern::PointerChase,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pattern::Sequential => "sequential",
+            Pattern::Strided => "strided",
+            Pattern::Random => "random",
+            Pattern::Zipf => "zipf",
+            Pattern::RowMajor => "row-major",
+            Pattern::ColumnMajor => "column-major",
+            Pattern::BlockedMatmul => "matmul",
+            Pattern::PointerChase => "pointer-chase",
+        }
+    }
+
+    /// Whether the pattern uses the seed and the number of accesses.
+    pub fn is_random(&self) -> bool {
+        matches!(self, Pattern::Random | Pattern::Zipf | Pattern::PointerChase)
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParsePatternError;
+impl fmt::Display for ParsePatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = Pattern::ALL.iter().map(Pattern::name).collect();
+        write!(f, "Invalid pattern, expected one of {}", names.join(", "))
+    }
+}
+
+impl Error for ParsePatternError {}
+
+impl FromStr for Pattern {
+    type Err = ParsePatternError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Pattern::ALL
+            .into_iter()
+            .find(|pattern| pattern.name().eq_ignore_ascii_case(s))
+            .ok_or(ParsePatternError)
+    }
+}
+
+/// Zipf and pointer chasing build a table with an entry per element before the first access, so
+/// larger counts are rejected instead of exhausting the memory.
+const MAX_TABLE_ELEMENTS: u64 = 1 << 24;
+
+#[derive(Debug, PartialEq)]
+pub enum WorkloadError {
+    ZeroElementSize,
+    ZeroCount,
+    ZeroStride,
+    ZeroBlock,
+    InvalidExponent(f64),
+    /// The accessed memory does not fit below the largest address.
+    TooLarge,
+    /// Zipf and pointer chasing keep a table of the elements, which would not fit into memory.
+    TooManyElements(Pattern),
+}
+
+impl fmt::Display for WorkloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkloadError::ZeroElementSize => write!(f, "The element size must not be zero"),
+            WorkloadError::ZeroCount => write!(f, "The element count must not be zero"),
+            WorkloadError::ZeroStride => write!(f, "The stride must not be zero"),
+            WorkloadError::ZeroBlock => write!(f, "The block size must not be zero"),
+            WorkloadError::InvalidExponent(exponent) => {
+                write!(f, "The Zipf exponent must not be negative, found {}", exponent)
+            }
+            WorkloadError::TooLarge => {
+                write!(f, "The accessed memory does not fit into 64-bit addresses")
+            }
+            WorkloadError::TooManyElements(pattern) => {
+                write!(f, "The {} pattern supports at most {} elements", pattern.name(), MAX_TABLE_ELEMENTS)
+            }
+        }
+    }
+}
+
+impl Error for WorkloadError {}
+
+/// Parameters of a synthetic trace. Fields that the pattern does not use are ignored.
+#[derive(Clone, Debug)]
+pub struct Workload {
+    pub pattern: Pattern,
+    /// Address of the first element.
+    pub base: u64,
+    /// Size of an element in bytes.
+    pub element_size: u64,
+    /// Number of elements, or rows and columns of the matrices.
+    pub count: u64,
+    /// Distance of the strided accesses in elements.
+    pub stride: u64,
+    /// Rows and columns of the blocks of the matrix multiplication.
+    pub block: u64,
+    /// Number of accesses of the random patterns. Defaults to `count`.
+    pub accesses: Option<u64>,
+    /// Exponent of the Zipf distribution. The larger it is, the hotter the first elements are.
+    pub exponent: f64,
+    pub seed: u64,
+}
+
+impl Default for Workload {
+    fn default() -> Self {
+        Workload {
+            pattern: Pattern::Sequential,
+            base: 0,
+            element_size: 8,
+            count: 64,
+            stride: 4,
+            block: 8,
+            accesses: None,
+            exponent: 1.0,
+            seed: 1,
+        }
+    }
+}
+
+/// Multiples of `step` below `end`.
+fn steps(end: u64, step: u64) -> impl Iterator<Item = u64> + Clone {
+    (0..end.div_ceil(step)).map(move |i| i * step)
+}
+
+impl Workload {
+    /// Checks the parameters that the pattern uses.
+    pub fn check(&self) -> Result<(), WorkloadError> {
+        let Workload { base, element_size, count, stride, block, exponent, .. } = *self;
+
+        if element_size == 0 {
+            return Err(WorkloadError::ZeroElementSize);
+        }
+        if count == 0 {
+            return Err(WorkloadError::ZeroCount);
+        }
+        if self.pattern == Pattern::Strided && stride == 0 {
+            return Err(WorkloadError::ZeroStride);
+        }
+        if self.pattern == Pattern::BlockedMatmul && block == 0 {
+            return Err(WorkloadError::ZeroBlock);
+        }
+        if self.pattern == Pattern::Zipf && !(exponent >= 0.0 && exponent.is_finite()) {
+            return Err(WorkloadError::InvalidExponent(exponent));
+        }
+        if matches!(self.pattern, Pattern::Zipf | Pattern::PointerChase) && count > MAX_TABLE_ELEMENTS {
+            return Err(WorkloadError::TooManyElements(self.pattern));
+        }
+
+        let n_elements = match self.pattern {
+            Pattern::RowMajor | Pattern::ColumnMajor => count.checked_mul(count),
+            Pattern::BlockedMatmul => count.checked_mul(count).and_then(|n| n.checked_mul(3)),
+            _ => Some(count),
+        };
+        n_elements
+            .and_then(|n| n.checked_mul(element_size))
+            .and_then(|size| size.checked_add(base))
+            .ok_or(WorkloadError::TooLarge)?;
+
+        Ok(())
+    }
+
+    /// Returns the addresses of the trace. They are generated while iterating, only Zipf and
+    /// pointer chasing keep a table with an entry per element.
+    pub fn addrs(&self) -> Result<Box<dyn Iterator<Item = u64> + Send>, WorkloadError> {
+        self.check()?;
+        let Workload { base, element_size, count, stride, block, .. } = *self;
+
+        let addr = move |index: u64| base + index * element_size;
+        let accesses = self.accesses.unwrap_or(count);
+        let mut rng = Rng::new(self.seed);
+
+        Ok(match self.pattern {
+            Pattern::Sequential => Box::new((0..count).map(addr)),
+            Pattern::Strided => Box::new(
+                (0..stride.min(count))
+                    .flat_map(move |start| steps(count - start, stride).map(move |i| start + i))
+                    .map(addr),
+            ),
+            Pattern::Random => Box::new((0..accesses).map(move |_| addr(rng.below(count)))),
+            Pattern::Zipf => {
+                let mut cdf = Vec::with_capacity(count as usize);
+                let mut total = 0.0;
+                for rank in 1..=count {
+                    total += 1.0 / (rank as f64).powf(self.exponent);
+                    cdf.push(total);
+                }
+
+                Box::new((0..accesses).map(move |_| {
+                    let x = rng.next_f64() * total;
+                    let index = cdf.partition_point(|&sum| sum <= x).min(cdf.len() - 1);
+                    addr(index as u64)
+                }))
+            }
+            Pattern::RowMajor => Box::new((0..count * count).map(addr)),
+            Pattern::ColumnMajor => {
+                Box::new((0..count * count).map(move |i| addr(i % count * count + i / count)))
+            }
+            Pattern::BlockedMatmul => {
+                let n = count;
+                let (a, b, c) = (0, n * n, 2 * n * n);
+                let range = move |start: u64| start..(start + block).min(n);
+
+                Box::new(steps(n, block).flat_map(move |ii| {
+                    steps(n, block).flat_map(move |jj| {
+                        steps(n, block).flat_map(move |kk| {
+                            range(ii).flat_map(move |i| {
+                                range(jj).flat_map(move |j| {
+                                    range(kk).flat_map(move |k| {
+                                        [a + i * n + k, b + k * n + j, c + i * n + j].map(addr)
+                                    })
+                                })
+                            })
+                        })
+                    })
+                }))
+            }
+            Pattern::PointerChase => {
+                // Sattolo's algorithm shuffles the nodes into a single cycle, so every node is
+                // visited before the list repeats.
+                let mut next: Vec<u64> = (0..count).collect();
+                for i in (1..next.len()).rev() {
+                    let j = rng.below(i as u64) as usize;
+                    next.swap(i, j);
+                }
+
+                let mut node = 0;
+                Box::new((0..accesses).map(move |_| {
+                    let current = node;
+                    node = next[node as usize];
+                    addr(current)
+                }))
+            }
+        })
+    }
+}
+
+/// A small SplitMix64 generator, so traces only depend on the seed.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number below `n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        ((u128::from(self.next_u64()) * u128::from(n)) >> 64) as u64
+    }
+
+    /// A number in [0, 1).
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Parses an address in hexadecimal with a "0x" prefix or in decimal.
+pub fn parse_address(text: &str) -> Option<u64> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs(pattern: Pattern, count: u64) -> Vec<u64> {
+        let workload = Workload {
+            pattern,
+            element_size: 1,
+            count,
+            stride: 4,
+            block: 1,
+            accesses: Some(10),
+            seed: 7,
+            ..Workload::default()
+        };
+        workload.addrs().unwrap().collect()
+    }
+
+    #[test]
+    fn patterns() {
+        assert_eq!(addrs(Pattern::Sequential, 6), [0, 1, 2, 3, 4, 5]);
+        assert_eq!(addrs(Pattern::Strided, 6), [0, 4, 1, 5, 2, 3]);
+        assert_eq!(addrs(Pattern::Random, 6), [2, 0, 5, 3, 2, 1, 2, 1, 0, 2]);
+        assert_eq!(addrs(Pattern::Zipf, 6), [0, 0, 4, 1, 1, 0, 1, 0, 0, 1]);
+        assert_eq!(addrs(Pattern::RowMajor, 3), [0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(addrs(Pattern::ColumnMajor, 3), [0, 3, 6, 1, 4, 7, 2, 5, 8]);
+        assert_eq!(
+            addrs(Pattern::BlockedMatmul, 2),
+            [0, 4, 8, 1, 6, 8, 0, 5, 9, 1, 7, 9, 2, 4, 10, 3, 6, 10, 2, 5, 11, 3, 7, 11]
+        );
+        assert_eq!(addrs(Pattern::PointerChase, 6), [0, 3, 2, 5, 1, 4, 0, 3, 2, 5]);
+    }
+
+    #[test]
+    fn pointer_chase_is_a_single_cycle() {
+        for seed in 1..20 {
+            let workload = Workload {
+                pattern: Pattern::PointerChase,
+                base: 0x1000,
+                count: 100,
+                accesses: Some(300),
+                seed,
+                ..Workload::default()
+            };
+            let addrs: Vec<u64> = workload.addrs().unwrap().collect();
+            for cycle in addrs.chunks(100) {
+                assert_eq!(cycle[0], 0x1000);
+                let mut sorted = cycle.to_vec();
+                sorted.sort_unstable();
+                assert!(sorted.iter().copied().eq((0..100).map(|i| 0x1000 + i * 8)));
+            }
+        }
+    }
+
+    #[test]
+    fn table_limit() {
+        for pattern in Pattern::ALL {
+            let workload = Workload { pattern, count: MAX_TABLE_ELEMENTS + 1, ..Workload::default() };
+            let expected = match pattern {
+                Pattern::Zipf | Pattern::PointerChase => Err(WorkloadError::TooManyElements(pattern)),
+                _ => Ok(()),
+            };
+            assert_eq!(workload.check(), expected, "{}", pattern.name());
+        }
+        for pattern in [Pattern::Zipf, Pattern::PointerChase] {
+            let workload = Workload { pattern, count: MAX_TABLE_ELEMENTS, ..Workload::default() };
+            assert_eq!(workload.check(), Ok(()));
+        }
+    }
+}
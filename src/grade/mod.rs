@@ -0,0 +1,355 @@
+// Grading of answer files against the simulation of a trace. An answer file is plain text, `#`
+// starts a comment:
+//
+//     # The result of every access of the first cache level, in order, optionally after its
+//     # hexadecimal address. hit and miss may be shortened to h and m.
+//     miss
+//     aca miss
+//     hit
+//     # The final content of a set: its index and the hexadecimal tag of every way, - if empty.
+//     set 0: 3b 2f 33 37
+//     set 3: 2a - - -
+//
+// Every access is worth a point, and so is every cache line that holds a block in the simulation or
+// in the answer. Empty lines only count if a tag was answered for them, so leaving out sets earns
+// nothing and filling empty ways with guesses costs points. Accesses without an answer are wrong,
+// sets that are not listed count as empty, so only the sets that are used have to be given.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead};
+
+use crate::sim::LevelResult;
+
+mod output;
+
+pub use output::write;
+
+#[derive(Debug)]
+pub enum AnswerError {
+    Io(io::Error),
+    /// A line is neither the result of an access nor the content of a set.
+    InvalidLine { line: usize, text: String },
+    /// A tag of a set is not a hexadecimal number or -.
+    InvalidTag { line: usize, text: String },
+    DuplicateSet { line: usize, set: u64 },
+    /// There are more results than the level has accesses.
+    TooManyAccesses { line: usize, accesses: usize },
+    /// The address of a result is not the address of the access it answers.
+    AddressMismatch { line: usize, index: usize, expected: u64, found: u64 },
+    SetOutOfRange { line: usize, set: u64, n_sets: u64 },
+    WrongWayCount { line: usize, set: u64, assoc: u64, found: usize },
+}
+
+impl fmt::Display for AnswerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnswerError::Io(err) => write!(f, "{}", err),
+            AnswerError::InvalidLine { line, text } => write!(
+                f,
+                "Line {}: Expected hit or miss, an address followed by hit or miss, or \"set N: TAGS\", found \"{}\"",
+                line, text
+            ),
+            AnswerError::InvalidTag { line, text } => {
+                write!(f, "Line {}: Expected a hexadecimal tag or -, found \"{}\"", line, text)
+            }
+            AnswerError::DuplicateSet { line, set } => write!(f, "Line {}: Set {} is given twice", line, set),
+            AnswerError::TooManyAccesses { line, accesses } => {
+                write!(f, "Line {}: The trace only has {} accesses", line, accesses)
+            }
+            AnswerError::AddressMismatch { line, index, expected, found } => write!(
+                f,
+                "Line {}: Access {} goes to {:x}, not {:x}",
+                line, index + 1, expected, found
+            ),
+            AnswerError::SetOutOfRange { line, set, n_sets } => {
+                write!(f, "Line {}: Set {} does not exist, the cache has {} sets", line, set, n_sets)
+            }
+            AnswerError::WrongWayCount { line, set, assoc, found } => write!(
+                f,
+                "Line {}: Set {} has {} ways, found {} tags",
+                line, set, assoc, found
+            ),
+        }
+    }
+}
+
+impl Error for AnswerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AnswerError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for AnswerError {
+    fn from(err: io::Error) -> Self {
+        AnswerError::Io(err)
+    }
+}
+
+/// The result of an access as answered on a line.
+struct AccessAnswer {
+    line: usize,
+    addr: Option<u64>,
+    hit: bool,
+}
+
+/// The content of a set as answered on a line, `None` for empty ways.
+struct SetAnswer {
+    line: usize,
+    tags: Vec<Option<u64>>,
+}
+
+/// The contents of an answer file, before it is compared to a simulation.
+pub struct Answers {
+    accesses: Vec<AccessAnswer>,
+    sets: BTreeMap<u64, SetAnswer>,
+}
+
+fn parse_hex(text: &str) -> Option<u64> {
+    let text = text.strip_prefix("0x").unwrap_or(text);
+    u64::from_str_radix(text, 16).ok()
+}
+
+fn parse_result(text: &str) -> Option<bool> {
+    match text.to_ascii_lowercase().as_str() {
+        "hit" | "h" => Some(true),
+        "miss" | "m" => Some(false),
+        _ => None,
+    }
+}
+
+impl Answers {
+    pub fn read(reader: impl BufRead) -> Result<Answers, AnswerError> {
+        let mut answers = Answers { accesses: Vec::new(), sets: BTreeMap::new() };
+
+        for (i, text) in reader.lines().enumerate() {
+            let text = text?;
+            let line = i + 1;
+            let content = text.split('#').next().unwrap_or_default().trim();
+            if content.is_empty() {
+                continue;
+            }
+            let invalid = || AnswerError::InvalidLine { line, text: content.to_string() };
+
+            if let Some((set, tags)) = content.split_once(':') {
+                let set = set
+                    .trim()
+                    .to_ascii_lowercase()
+                    .strip_prefix("set")
+                    .and_then(|index| index.trim().parse().ok())
+                    .ok_or_else(invalid)?;
+                let tags = tags
+                    .split_whitespace()
+                    .map(|tag| match tag {
+                        "-" => Ok(None),
+                        _ => parse_hex(tag).map(Some).ok_or_else(|| AnswerError::InvalidTag { line, text: tag.to_string() }),
+                    })
+                    .collect::<Result<_, _>>()?;
+                if answers.sets.insert(set, SetAnswer { line, tags }).is_some() {
+                    return Err(AnswerError::DuplicateSet { line, set });
+                }
+                continue;
+            }
+
+            let words: Vec<&str> = content.split_whitespace().collect();
+            let (addr, hit) = match words[..] {
+                [result] => (None, parse_result(result)),
+                [addr, result] => (Some(parse_hex(addr).ok_or_else(invalid)?), parse_result(result)),
+                _ => (None, None),
+            };
+            answers.accesses.push(AccessAnswer { line, addr, hit: hit.ok_or_else(invalid)? });
+        }
+
+        Ok(answers)
+    }
+
+    /// Compares the answers to the accesses and final lines of a simulated level, which has to be
+    /// simulated with history.
+    pub fn grade(&self, level: &LevelResult) -> Result<Grade, AnswerError> {
+        let cache = &level.cache;
+        if let Some(answer) = self.accesses.get(level.events.len()) {
+            return Err(AnswerError::TooManyAccesses { line: answer.line, accesses: level.events.len() });
+        }
+        for (index, (answer, event)) in self.accesses.iter().zip(&level.events).enumerate() {
+            match answer.addr {
+                Some(addr) if addr != event.addr => {
+                    return Err(AnswerError::AddressMismatch { line: answer.line, index, expected: event.addr, found: addr })
+                }
+                _ => {}
+            }
+        }
+        for (&set, answer) in &self.sets {
+            if set >= cache.n_sets() {
+                return Err(AnswerError::SetOutOfRange { line: answer.line, set, n_sets: cache.n_sets() });
+            }
+            if answer.tags.len() as u64 != cache.assoc() {
+                return Err(AnswerError::WrongWayCount { line: answer.line, set, assoc: cache.assoc(), found: answer.tags.len() });
+            }
+        }
+
+        let accesses = level
+            .events
+            .iter()
+            .enumerate()
+            .map(|(i, event)| AccessGrade {
+                time: event.time,
+                addr: event.addr,
+                hit: event.hit,
+                answer: self.accesses.get(i).map(|answer| answer.hit),
+            })
+            .collect();
+
+        let assoc = cache.assoc() as usize;
+        let lines = level
+            .lines
+            .chunks(assoc)
+            .enumerate()
+            .flat_map(|(set, ways)| {
+                let answer = self.sets.get(&(set as u64));
+                ways.iter().enumerate().map(move |(way, history)| LineGrade {
+                    set: set as u64,
+                    way: way as u64,
+                    tag: history.last().map(|entry| entry.tag()),
+                    answer: answer.and_then(|answer| answer.tags[way]),
+                })
+            })
+            // Lines that are empty in both are right without any answer, so they are not graded.
+            .filter(|line: &LineGrade| line.tag.is_some() || line.answer.is_some())
+            .collect();
+
+        Ok(Grade { accesses, lines })
+    }
+}
+
+pub struct AccessGrade {
+    pub time: u64,
+    pub addr: u64,
+    pub hit: bool,
+    /// Whether the answer was a hit, `None` without an answer.
+    pub answer: Option<bool>,
+}
+
+impl AccessGrade {
+    pub fn is_correct(&self) -> bool {
+        self.answer == Some(self.hit)
+    }
+}
+
+/// A graded cache line. `None` stands for an empty line.
+pub struct LineGrade {
+    pub set: u64,
+    pub way: u64,
+    pub tag: Option<u64>,
+    pub answer: Option<u64>,
+}
+
+impl LineGrade {
+    pub fn is_correct(&self) -> bool {
+        self.answer == self.tag
+    }
+}
+
+/// The graded accesses, in order, and lines, ordered by set and way. Only lines that hold a block
+/// in the simulation or the answer are graded.
+pub struct Grade {
+    pub accesses: Vec<AccessGrade>,
+    pub lines: Vec<LineGrade>,
+}
+
+impl Grade {
+    pub fn score(&self) -> usize {
+        self.accesses.iter().filter(|access| access.is_correct()).count()
+            + self.lines.iter().filter(|line| line.is_correct()).count()
+    }
+
+    pub fn max_score(&self) -> usize {
+        self.accesses.len() + self.lines.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::slice;
+
+    use crate::sim::{self, Hierarchy};
+
+    use super::*;
+
+    const ANSWERS: &str = include_str!("../../test/evict.answers");
+
+    /// Simulates `test/evict` with history, like `grade` does.
+    fn evict() -> LevelResult {
+        let (cache, addrs) = sim::read(include_str!("../../test/evict").as_bytes(), true).unwrap();
+        let cache = cache.unwrap();
+
+        let mut hierarchy = Hierarchy::new(slice::from_ref(&cache), true);
+        for addr in addrs {
+            hierarchy.access(addr.unwrap());
+        }
+        let (lines, events, stats) = hierarchy.finish().pop().unwrap();
+        LevelResult { name: "Cache".to_string(), cache, lines, events, stats }
+    }
+
+    fn grade(answers: &str) -> Result<Grade, AnswerError> {
+        Answers::read(answers.as_bytes())?.grade(&evict())
+    }
+
+    #[test]
+    fn full_marks() {
+        let grade = grade(ANSWERS).unwrap();
+        assert_eq!((grade.score(), grade.max_score()), (16, 16));
+        assert!(grade.accesses.iter().all(AccessGrade::is_correct));
+    }
+
+    #[test]
+    fn mistakes() {
+        // The first access hits, the last has no answer and a tag of set 0 is wrong.
+        let answers = ANSWERS
+            .replacen("abe miss", "abe h", 1)
+            .replacen("dca hit\n\n", "\n", 1)
+            .replace("set 0: 3b", "set 0: 2b");
+        let grade = grade(&answers).unwrap();
+        assert_eq!((grade.score(), grade.max_score()), (13, 16));
+        assert_eq!(grade.accesses[10].answer, None);
+    }
+
+    #[test]
+    fn only_lines_with_blocks_are_graded() {
+        // Without sets, the five lines of the simulation are wrong.
+        let accesses = ANSWERS.split("set").next().unwrap();
+        let grade = grade(accesses).unwrap();
+        assert_eq!((grade.score(), grade.max_score()), (11, 16));
+        assert!(grade.lines.iter().all(|line| line.tag.is_some() && line.answer.is_none()));
+
+        // A tag in an empty line is graded as well.
+        let grade = self::grade(&format!("{}set 1: - 5 - -", ANSWERS)).unwrap();
+        assert_eq!((grade.score(), grade.max_score()), (16, 17));
+        let guess = grade.lines.iter().find(|line| line.set == 1).unwrap();
+        assert_eq!((guess.way, guess.tag, guess.answer), (1, None, Some(5)));
+    }
+
+    #[test]
+    fn invalid_answers() {
+        let too_many = format!("{}\nhit", ANSWERS.split("set").next().unwrap());
+        assert!(matches!(grade(&too_many), Err(AnswerError::TooManyAccesses { line: 15, accesses: 11 })));
+        assert!(matches!(
+            grade("miss\nacb miss"),
+            Err(AnswerError::AddressMismatch { line: 2, index: 1, expected: 0xaca, found: 0xacb })
+        ));
+        assert!(matches!(grade("set 4: - - - -"), Err(AnswerError::SetOutOfRange { line: 1, set: 4, n_sets: 4 })));
+        assert!(matches!(
+            grade("set 0: 3b 2f"),
+            Err(AnswerError::WrongWayCount { line: 1, set: 0, assoc: 4, found: 2 })
+        ));
+        assert!(matches!(
+            grade("set 0: - - - -\n# again\nset 0: - - - -"),
+            Err(AnswerError::DuplicateSet { line: 3, set: 0 })
+        ));
+        assert!(matches!(grade("set 0: 3b 2f 33 zz"), Err(AnswerError::InvalidTag { line: 1, .. })));
+        assert!(matches!(grade("hit hit"), Err(AnswerError::InvalidLine { line: 1, .. })));
+    }
+}
@@ -0,0 +1,319 @@
+// Grades as text for people, or for import into other programs as JSON or CSV. Version 1 of the
+// JSON schema:
+//
+//     {
+//       "version": 1,
+//       "trace": "test/evict",
+//       "level": "Cache",
+//       "answers": [
+//         {
+//           "file": "alice.txt", "score": 14, "max_score": 16, "error": null,
+//           "accesses": [ { "time": 0, "address": 2750, "hit": false, "answer": true, "correct": false } ],
+//           "lines": [ { "set": 0, "way": 0, "tag": 59, "answer": 59, "correct": true } ]
+//         }
+//       ]
+//     }
+//
+// There is an entry in `answers` for every answer file, in the order they were given. Files that
+// could not be graded have a null `score` and `max_score`, an `error` message and no accesses or
+// lines. `answer` is null for accesses without an answer, `tag` or `answer` of lines is null for
+// an empty way.
+//
+// The CSV table has the columns
+//
+//     file,record,time,address,hit,set,way,tag,answer,correct,score,max_score,error
+//
+// and starts every file with a `score` record that has `score` and `max_score`, or `error`,
+// followed by an `access` record for every access and a `line` record for every graded line. The
+// columns mean the same as in JSON, empty cells stand for null. Numbers are decimal, also in CSV.
+//
+// Only lines that hold a block in the simulation or in the answer are graded and listed, so
+// `max_score` can differ between answer files of the same trace.
+
+use std::io::{self, Write};
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use crate::export::OutputFormat;
+
+use super::{AnswerError, Grade};
+
+/// The newest version of the schema.
+pub const VERSION: u32 = 1;
+
+/// The grades of the answer files, named by their file, of the level `level` of the trace `trace`.
+pub fn write(
+    writer: &mut impl Write,
+    format: OutputFormat,
+    trace: &str,
+    level: &str,
+    grades: &[(String, Result<Grade, AnswerError>)],
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer(&mut *writer, &Document { trace, level, grades })?;
+            writeln!(writer)?;
+        }
+        OutputFormat::Csv => write_csv(writer, grades)?,
+        _ => write_text(writer, grades)?,
+    }
+
+    writer.flush()
+}
+
+fn hit_or_miss(hit: bool) -> &'static str {
+    if hit { "hit" } else { "miss" }
+}
+
+fn write_text(writer: &mut impl Write, grades: &[(String, Result<Grade, AnswerError>)]) -> io::Result<()> {
+    for (i, (file, grade)) in grades.iter().enumerate() {
+        if i > 0 {
+            writeln!(writer)?;
+        }
+        let grade = match grade {
+            Ok(grade) => grade,
+            Err(err) => {
+                writeln!(writer, "{}: {}", file, err)?;
+                continue;
+            }
+        };
+        writeln!(writer, "{}: {}/{} points", file, grade.score(), grade.max_score())?;
+
+        let mark = |correct: bool| if correct { "correct" } else { "wrong" };
+        let digits = |n: u64| n.to_string().len();
+        let hex_digits = |n: u64| format!("{:x}", n).len();
+        let time = grade.accesses.iter().map(|access| digits(access.time)).fold("Time".len(), usize::max);
+        let addr = grade.accesses.iter().map(|access| hex_digits(access.addr)).fold("Address".len(), usize::max);
+        writeln!(writer, "{:>time$}  {:>addr$}  Result  Answer", "Time", "Address")?;
+        for access in &grade.accesses {
+            writeln!(
+                writer,
+                "{:>time$}  {:>addr$x}  {:<6}  {:<6}  {}",
+                access.time,
+                access.addr,
+                hit_or_miss(access.hit),
+                access.answer.map_or("-", hit_or_miss),
+                mark(access.is_correct()),
+            )?;
+        }
+
+        let format_tag = |tag: Option<u64>| tag.map_or("-".to_string(), |tag| format!("{:x}", tag));
+        let set = grade.lines.iter().map(|line| digits(line.set)).fold("Set".len(), usize::max);
+        let way = grade.lines.iter().map(|line| digits(line.way)).fold("Way".len(), usize::max);
+        let tag = grade
+            .lines
+            .iter()
+            .flat_map(|line| [line.tag, line.answer])
+            .map(|tag| format_tag(tag).len())
+            .fold("Answer".len(), usize::max);
+        writeln!(writer, "{:>set$}  {:>way$}  {:>tag$}  {:>tag$}", "Set", "Way", "Tag", "Answer")?;
+        for line in &grade.lines {
+            writeln!(
+                writer,
+                "{:>set$}  {:>way$}  {:>tag$}  {:>tag$}  {}",
+                line.set,
+                line.way,
+                format_tag(line.tag),
+                format_tag(line.answer),
+                mark(line.is_correct()),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Quotes `field` if it contains characters that have a meaning in CSV.
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn write_csv(writer: &mut impl Write, grades: &[(String, Result<Grade, AnswerError>)]) -> io::Result<()> {
+    writeln!(writer, "file,record,time,address,hit,set,way,tag,answer,correct,score,max_score,error")?;
+
+    let optional = |value: Option<u64>| value.map_or(String::new(), |value| value.to_string());
+    for (file, grade) in grades {
+        let file = escape(file);
+        let grade = match grade {
+            Ok(grade) => grade,
+            Err(err) => {
+                writeln!(writer, "{},score,,,,,,,,,,,{}", file, escape(&err.to_string()))?;
+                continue;
+            }
+        };
+
+        writeln!(writer, "{},score,,,,,,,,,{},{},", file, grade.score(), grade.max_score())?;
+        for access in &grade.accesses {
+            writeln!(
+                writer,
+                "{},access,{},{},{},,,,{},{},,,",
+                file,
+                access.time,
+                access.addr,
+                access.hit,
+                access.answer.map_or(String::new(), |hit| hit.to_string()),
+                access.is_correct(),
+            )?;
+        }
+        for line in &grade.lines {
+            writeln!(
+                writer,
+                "{},line,,,,{},{},{},{},{},,,",
+                file,
+                line.set,
+                line.way,
+                optional(line.tag),
+                optional(line.answer),
+                line.is_correct(),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+struct Document<'a> {
+    trace: &'a str,
+    level: &'a str,
+    grades: &'a [(String, Result<Grade, AnswerError>)],
+}
+
+impl Serialize for Document<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut document = serializer.serialize_struct("Document", 4)?;
+        document.serialize_field("version", &VERSION)?;
+        document.serialize_field("trace", self.trace)?;
+        document.serialize_field("level", self.level)?;
+        document.serialize_field("answers", &Files(self.grades))?;
+        document.end()
+    }
+}
+
+struct Files<'a>(&'a [(String, Result<Grade, AnswerError>)]);
+
+impl Serialize for Files<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(|(file, grade)| File { file, grade }))
+    }
+}
+
+struct File<'a> {
+    file: &'a str,
+    grade: &'a Result<Grade, AnswerError>,
+}
+
+impl Serialize for File<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut file = serializer.serialize_struct("File", 6)?;
+        file.serialize_field("file", self.file)?;
+        match self.grade {
+            Ok(grade) => {
+                file.serialize_field("score", &Some(grade.score()))?;
+                file.serialize_field("max_score", &Some(grade.max_score()))?;
+                file.serialize_field("error", &None::<String>)?;
+                file.serialize_field("accesses", &Accesses(grade))?;
+                file.serialize_field("lines", &Lines(grade))?;
+            }
+            Err(err) => {
+                file.serialize_field("score", &None::<usize>)?;
+                file.serialize_field("max_score", &None::<usize>)?;
+                file.serialize_field("error", &Some(err.to_string()))?;
+                file.serialize_field("accesses", &[(); 0])?;
+                file.serialize_field("lines", &[(); 0])?;
+            }
+        }
+        file.end()
+    }
+}
+
+struct Accesses<'a>(&'a Grade);
+
+impl Serialize for Accesses<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.accesses.iter().map(|access| Access {
+            time: access.time,
+            address: access.addr,
+            hit: access.hit,
+            answer: access.answer,
+            correct: access.is_correct(),
+        }))
+    }
+}
+
+#[derive(serde::Serialize)]
+struct Access {
+    time: u64,
+    address: u64,
+    hit: bool,
+    answer: Option<bool>,
+    correct: bool,
+}
+
+struct Lines<'a>(&'a Grade);
+
+impl Serialize for Lines<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.lines.iter().map(|line| Line {
+            set: line.set,
+            way: line.way,
+            tag: line.tag,
+            answer: line.answer,
+            correct: line.is_correct(),
+        }))
+    }
+}
+
+#[derive(serde::Serialize)]
+struct Line {
+    set: u64,
+    way: u64,
+    tag: Option<u64>,
+    answer: Option<u64>,
+    correct: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{AccessGrade, LineGrade};
+    use super::*;
+
+    #[test]
+    fn csv_columns() {
+        let grade = Grade {
+            accesses: vec![
+                AccessGrade { time: 0, addr: 0xabe, hit: false, answer: Some(false) },
+                AccessGrade { time: 1, addr: 0xaca, hit: true, answer: None },
+            ],
+            lines: vec![
+                LineGrade { set: 0, way: 0, tag: Some(0x2b), answer: Some(0x3b) },
+                LineGrade { set: 1, way: 2, tag: None, answer: Some(5) },
+            ],
+        };
+        let grades = [
+            ("alice.txt".to_string(), Ok(grade)),
+            ("bob.txt".to_string(), Err(AnswerError::DuplicateSet { line: 3, set: 1 })),
+        ];
+
+        let mut bytes = Vec::new();
+        write(&mut bytes, OutputFormat::Csv, "test/evict", "Cache", &grades).unwrap();
+        let csv = String::from_utf8(bytes).unwrap();
+
+        assert_eq!(
+            csv.lines().collect::<Vec<_>>(),
+            [
+                "file,record,time,address,hit,set,way,tag,answer,correct,score,max_score,error",
+                "alice.txt,score,,,,,,,,,1,4,",
+                "alice.txt,access,0,2750,false,,,,false,true,,,",
+                "alice.txt,access,1,2762,true,,,,,false,,,",
+                "alice.txt,line,,,,0,0,43,59,false,,,",
+                "alice.txt,line,,,,1,2,,5,false,,,",
+                "bob.txt,score,,,,,,,,,,,Line 3: Set 1 is given twice",
+            ]
+        );
+        assert!(csv.lines().all(|row| row.split(',').count() == 13));
+    }
+}
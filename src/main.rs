@@ -1,44 +1,50 @@
-use std::ffi::OsString;
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::path::{Path, PathBuf};
-use std::{error::Error, thread, env};
+use std::{error::Error, thread, env, process};
 
-use gtk::gio::{ApplicationFlags, ApplicationCommandLine, Cancellable};
-use gtk::glib::{MainContext, Priority};
+use gtk::gio::{self, ApplicationFlags, ApplicationCommandLine, Cancellable};
+use gtk::glib::{MainContext, Priority, SignalHandlerId};
 use gtk::pango::EllipsizeMode;
-use gtk::{prelude::*, ScrolledWindow, PolicyType, Button, Orientation, Label, Align, Separator, FileDialog, Window, DialogError, Spinner};
-use gtk::{glib};
-use sim::{CacheEntry, CacheStats, CacheDesc};
+use gtk::{prelude::*, Button, Label, FileDialog, Window, DialogError};
+use gtk::{glib, MenuButton};
+use sim::{AccessEvent, CacheEntry, CacheDesc, Hierarchy, LevelResult};
 use glib::clone;
+use document::CacheCacheDocument;
 use window::CacheCacheWindow;
 
+use cli::{Args, Outcome};
+use config::Level;
+use sim::CacheOverrides;
+use trace::{Accesses, Format, Trace};
 use libadwaita::{Application, HeaderBar};
+mod analyze;
+mod cli;
+mod config;
+mod document;
+mod event_model;
+mod exercise;
+mod export;
+mod generate;
+mod grade;
+mod quiz;
+mod repl;
+mod result_model;
 mod sim;
+mod sweep;
+mod trace;
 mod window;
 
 const APP_ID: &str = "com.github.maxi0604.CacheCache";
-type SimResult = (CacheLineVec, CacheDesc, Vec<u64>, CacheStats);
+/// All levels of a simulated cache hierarchy, first level first.
+type SimResult = Vec<LevelResult>;
 
 fn main() -> glib::ExitCode {
-    let mut args = env::args();
-    if args.by_ref().any(|x| x == "--no-window") {
-        let (cache, addrs) = sim::read(&args.next().expect("Missing argument. --no-window implies path").into()).unwrap();
-        let (lines, stats) = sim::simulate(&cache, &addrs);
+    let args = match cli::run(env::args().collect()) {
+        Outcome::Gui(args) => args,
+        Outcome::Exit(code) => process::exit(code),
+    };
 
-        for (i, line) in lines.iter().enumerate() {
-            println!("{}", sim::format_cache_line(line, (i as u64 / cache.n_sets()).try_into().unwrap()));
-        }
-
-        println!("Hits: {1}/{0}. Misses: {2}/{0}. Evictions: {3}/{0}. Tag Bits: {4}, Index Bits: {5} Offset Bits: {6}",
-            addrs.len(),
-            stats.hits(),
-            stats.misses(),
-            stats.evictions(),
-            cache.tag_bits(),
-            cache.idx_bits(),
-            cache.offset_bits(),
-        );
-        return 0.into();
-    }
     let app = Application::builder()
         .application_id(APP_ID)
         .flags(ApplicationFlags::HANDLES_COMMAND_LINE)
@@ -46,17 +52,60 @@ fn main() -> glib::ExitCode {
 
     app.connect_command_line(build_ui);
 
-    app.run()
+    app.run_with_args(&args)
+}
+
+fn build_ui(app: &Application, command_line: &ApplicationCommandLine) -> i32 {
+    // Validated in main, but the primary instance is also passed the arguments of remote ones.
+    let arguments = command_line.arguments().iter().skip(1).map(|arg| arg.to_string_lossy().into_owned()).collect();
+    let (options, path) = match cli::gui_options(Args::new(arguments)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            return cli::EXIT_USAGE;
+        }
+    };
+
+    // Traces opened by remote instances become tabs of the active window.
+    let window = match app.active_window().and_downcast::<CacheCacheWindow>() {
+        Some(window) => window,
+        None => build_window(app, options.clone()),
+    };
+    window.add_document(&CacheCacheDocument::new(options, path));
+    window.present();
+    0
 }
 
-enum SimulationCommunication {
-    Success(SimResult),
-    Failure,
-    Run
+/// The document of the selected tab with the handlers that update the header bar when it changes.
+type SelectedDocument = (CacheCacheDocument, Vec<SignalHandlerId>);
+
+/// The document of the selected tab, or a new empty one if the window has no tabs.
+fn current_document(window: &CacheCacheWindow, options: &SimOptions) -> CacheCacheDocument {
+    window.document().unwrap_or_else(|| {
+        let document = CacheCacheDocument::new(options.clone(), None);
+        window.add_document(&document);
+        document
+    })
 }
 
-fn build_ui(app: &Application, command_line: &ApplicationCommandLine) -> i32 {
+/// Opens the trace at `path` in the selected tab if it has none yet, otherwise in a new tab.
+fn open_document(window: &CacheCacheWindow, options: &SimOptions, path: PathBuf) -> CacheCacheDocument {
+    match window.document().filter(|document| !document.path_buf().is_file()) {
+        Some(document) => {
+            document.set_path_buf(path);
+            document
+        }
+        None => {
+            let document = CacheCacheDocument::new(options.clone(), Some(path));
+            window.add_document(&document);
+            document
+        }
+    }
+}
 
+/// Builds a window without tabs. The buttons of the header bar act on the selected tab, new tabs
+/// start with `options`.
+fn build_window(app: &Application, options: SimOptions) -> CacheCacheWindow {
     let window = CacheCacheWindow::new(app);
 
     let title_label = Label::builder()
@@ -70,219 +119,304 @@ fn build_ui(app: &Application, command_line: &ApplicationCommandLine) -> i32 {
         .title_widget(&title_label)
         .build();
 
+    // Tabs dragged out of the window are moved to a new one.
+    window.tab_view().connect_create_window(clone!(@weak app, @strong options => @default-return None, move |_| {
+        let window = build_window(&app, options.clone());
+        window.present();
+        Some(window.tab_view())
+    }));
 
-    let arguments: Vec<OsString> = command_line.arguments();
-    if let Some(os_string) = arguments.get(1) {
-        let mut some_path_buf = PathBuf::new();
-        some_path_buf.push(Path::new(os_string));
-        window.set_path_buf(some_path_buf);
-    }
-
-    let scrolled_window = ScrolledWindow::builder()
-        .hscrollbar_policy(PolicyType::Automatic)
-        .min_content_width(150)
-        .vexpand(true)
-        .build();
-
-    let separator_top = Separator::new(Orientation::Horizontal);
-    let separator_bottom = Separator::new(Orientation::Horizontal);
-    separator_bottom.set_visible(false);
-
-    let file_display_label = Label::builder()
-        .label("No File Selected")
-        .build();
-    let file_display_spinner = Spinner::builder()
-        .halign(Align::End)
-        .build();
-
-    window.bind_property("path-buf", &file_display_label, "label")
-        .transform_to(|_, path_buf: PathBuf| {
-            if let Some(file_str) = path_buf.to_str().to_owned() {
-                Some(file_str.to_value())
-            } else if path_buf.is_file() {
-                Some("Could not parse file name to string".to_value())
-            } else {
-                Some("No File Selected".to_value())
-            }
-        })
-        .build();
-
-    let file_display = gtk::Box::builder()
-        .spacing(10)
-        .margin_top(10)
-        .margin_end(10)
-        .margin_start(10)
-        .margin_bottom(10)
-        .orientation(Orientation::Horizontal)
-        .hexpand(true)
-        .build();
-
-    file_display.append(&file_display_label);
-    file_display.append(&file_display_spinner);
-    
     let simulate_button = Button::builder()
-        .sensitive(window.path_buf().is_file())
+        .sensitive(false)
         .hexpand(true)
         .icon_name("media-playback-start-symbolic")
         .build();
 
-    let stats_showcase = Label::builder().visible(false).build();
+    simulate_button.connect_clicked(clone!(@weak window => move |_| {
+        if let Some(document) = window.document() {
+            document.simulate();
+        }
+    }));
 
-    stats_showcase.bind_property("visible", &separator_bottom, "visible")
-        .bidirectional()
+    let open_file_button = Button::builder()
+        .icon_name("document-open-symbolic")
+        .hexpand(true)
         .build();
 
-    let (sim_sender, sim_receiver) = MainContext::channel(Priority::default());
-
-    simulate_button.connect_clicked(clone!(@weak window => move |_| {
-        let sim_sender = sim_sender.clone();
-        let path_buf = window.path_buf();
-        if path_buf.is_file() {
-            let some_path_buf = path_buf;
-            thread::spawn(move || {
-                sim_sender.send(SimulationCommunication::Run).expect("Could not send through channel");
-
-                match run_sim(&some_path_buf) {
-                    Ok(result) => {
-                        sim_sender.send(SimulationCommunication::Success(result)).expect("Could not send through channel");
+    open_file_button.connect_clicked(clone!(@weak window, @strong options =>
+        move |_| {
+            let file_dialogue = FileDialog::new();
+            file_dialogue.open(Window::NONE, Cancellable::NONE, clone!(@strong options => move |result| {
+                match result {
+                    Ok(file) => {
+                        if let Some(path) = file.path() {
+                            open_document(&window, &options, path);
+                        }
                     },
                     Err(err) => {
-                        eprintln!("run_sim: {}", err);
-                        sim_sender.send(SimulationCommunication::Failure).expect("Could not send through channel");
-                    }
-                }
-
-            });
-        } else {
-            eprintln!("no file selected");
-        }
-    }));
-
-    let (stats_sender, stats_receiver) = MainContext::channel(Priority::default());
-
-    sim_receiver.attach(None, clone!(@weak simulate_button, @weak scrolled_window => @default-return Continue(false),
-        move |result| {
-            let stats_sender = stats_sender.clone();
-            match result {
-                SimulationCommunication::Success((lines, cache, addrs, stats)) => {
-                    simulate_button.set_sensitive(true);
-                    
-                    let grid = gtk::Grid::builder()
-                        .margin_end(10)
-                        .margin_top(10)
-                        .margin_start(10)
-                        .margin_bottom(10)
-                        .column_spacing(10)
-                        .build();
-
-                    for (i, line) in lines.iter().enumerate() {
-                        let line_index: i32 = (i as i32).try_into().unwrap();
-
-                        let li: u64 = (line_index as u64 / cache.n_sets()).try_into().unwrap();
-
-                        let line_label = Label::builder().label(format!("{}", li)).halign(Align::End).build();
-
-                        grid.attach(&line_label, 0, line_index, 2, 1);
-
-                        if line.is_empty() {
-                            let label = Label::builder().label("-").build();
-                            grid.attach(&label, 2, line_index, 1, 1);
-                        } else {
-                            let mut column_index: i32 = 2;
-
-                            for entry in line.iter() {
-                                let label = Label::builder().label(format!("{:x} ({})", entry.tag(), entry.entered())).build();
-                                grid.attach(&label, column_index, line_index, 1, 1);
-                                column_index += 1;
-                            }
+                        match err.kind::<DialogError>() {
+                            Some(DialogError::Dismissed) => {},
+                            _ => current_document(&window, &options).add_toast(err.message()),
                         }
                     }
-
-                    scrolled_window.set_child(Some(&grid));
-                    stats_sender.send(Some((cache, addrs, stats))).expect("Could not send through stats channel");
-                },
-                SimulationCommunication::Failure => {
-                    simulate_button.set_sensitive(true);
-                },
-                SimulationCommunication::Run => {
-                    simulate_button.set_sensitive(false);
                 }
-            }
-            Continue(true)
+            }))
         }
     ));
 
-    let container_box = gtk::Box::builder()
-        .orientation(Orientation::Vertical)
+    let new_tab_button = Button::builder()
+        .icon_name("tab-new-symbolic")
+        .tooltip_text("New Tab")
+        .hexpand(true)
         .build();
 
-    stats_receiver.attach(None, clone!(@weak stats_showcase => @default-return Continue(false), 
-        move |stats: Option<(CacheDesc, Vec<u64>, CacheStats)>| {
-            match stats {
-                Some((_cache, addrs, stats)) => {
-                    stats_showcase.set_label(format!("Hits: {1}/{0}. Misses: {2}/{0}. Evictions: {3}/{0}", addrs.len(), stats.hits(), stats.misses(), stats.evictions()).as_str());
-                    stats_showcase.set_visible(true);
-                }
-                None => {
-                    stats_showcase.set_visible(false);
-                }
-            }
-            Continue(true)
-        }
-    ));
+    new_tab_button.connect_clicked(clone!(@weak window, @strong options => move |_| {
+        window.add_document(&CacheCacheDocument::new(options.clone(), None));
+    }));
 
-    let open_file_button = Button::builder()
-        .icon_name("document-open-symbolic")
+    let open_config_button = Button::builder()
+        .icon_name("document-properties-symbolic")
+        .tooltip_text("Open Cache Configuration")
         .hexpand(true)
         .build();
 
-    container_box.append(&file_display);
-    container_box.append(&separator_top);
-    container_box.append(&scrolled_window);
-    container_box.append(&separator_bottom);
-    container_box.append(&stats_showcase);
-
-    window.set_child(Some(&container_box));
-
-    open_file_button.connect_clicked(clone!(@weak window, @weak file_display_spinner, @weak simulate_button => 
+    open_config_button.connect_clicked(clone!(@weak window, @strong options =>
         move |_| {
-            let file_dialogue = FileDialog::new();
-            simulate_button.set_sensitive(false);
-            file_display_spinner.set_spinning(true);
-            file_dialogue.open(Window::NONE, Cancellable::NONE, move |result| {
+            let file_dialogue = FileDialog::builder()
+                .title("Open Cache Configuration")
+                .build();
+            file_dialogue.open(Window::NONE, Cancellable::NONE, clone!(@strong options => move |result| {
+                let document = current_document(&window, &options);
                 match result {
                     Ok(file) => {
                         if let Some(path) = file.path() {
-                            window.set_path_buf(path);
+                            document.set_config_path(path);
                         }
-                        simulate_button.set_sensitive(true);
                     },
                     Err(err) => {
-                        if let Some(dialog_error) = err.kind::<DialogError>() {
-                            dbg!(dialog_error);
+                        if err.kind::<DialogError>() != Some(DialogError::Dismissed) {
+                            document.add_toast(err.message());
                         }
                     }
                 }
-                file_display_spinner.set_spinning(false);
-            })
+            }))
         }
     ));
 
+    let generate_button = Button::builder()
+        .icon_name("document-new-symbolic")
+        .tooltip_text("Generate Trace")
+        .hexpand(true)
+        .build();
+
+    generate_button.connect_clicked(clone!(@weak window, @strong options => move |_| {
+        generate::dialog::show(&window, clone!(@weak window, @strong options => move |path| {
+            let message = format!("Generated {}", path.display());
+            open_document(&window, &options, path).add_toast(&message);
+        }));
+    }));
+
+    // Reports are written from a fresh simulation of the trace, so no result has to be kept.
+    let export_report_action = gio::SimpleAction::new("export-report", None);
+
+    export_report_action.connect_activate(clone!(@weak window => move |_, _| {
+        let Some(document) = window.document() else {
+            return;
+        };
+        let path_buf = document.path_buf();
+        let options = document.sim_options();
+        let name = path_buf.file_stem().map_or("report".into(), |stem| stem.to_string_lossy());
+        let file_dialogue = FileDialog::builder()
+            .title("Export HTML Report")
+            .initial_name(format!("{}.html", name))
+            .build();
+
+        // The result is reported in the tab of the trace, wherever it is by then.
+        let (report_sender, report_receiver) = MainContext::channel(Priority::default());
+        report_receiver.attach(None, clone!(@weak document => @default-return Continue(false),
+            move |result: Result<PathBuf, String>| {
+                let message = match result {
+                    Ok(report_path) => format!("Exported {}", report_path.display()),
+                    Err(message) => message,
+                };
+                document.add_toast(&message);
+                Continue(false)
+            }
+        ));
+
+        file_dialogue.save(Some(&window), Cancellable::NONE, clone!(@weak document => move |result| {
+            let report_path = match result.map(|file| file.path()) {
+                Ok(Some(report_path)) => report_path,
+                Ok(None) => {
+                    document.add_toast("Reports can only be saved to local files");
+                    return;
+                }
+                Err(err) => {
+                    if err.kind::<DialogError>() != Some(DialogError::Dismissed) {
+                        document.add_toast(err.message());
+                    }
+                    return;
+                }
+            };
+
+            thread::spawn(move || {
+                let result = run_sim(&path_buf, &options, true)
+                    .map_err(|err| format!("Could not simulate {}: {}", path_buf.display(), err))
+                    .and_then(|levels| cli::write_report(&report_path, &path_buf, &levels)
+                        .map_err(|err| format!("Could not write {}: {}", report_path.display(), err)))
+                    .map(|()| report_path);
+                report_sender.send(result).expect("Could not send through channel");
+            });
+        }));
+    }));
+    window.add_action(&export_report_action);
+
+    // The quiz asks the accesses of the first level, which may be missing from the shown result.
+    let quiz_action = gio::SimpleAction::new("quiz", None);
+
+    quiz_action.connect_activate(clone!(@weak window => move |_, _| {
+        let Some(document) = window.document() else {
+            return;
+        };
+        let path_buf = document.path_buf();
+        let options = document.sim_options();
+
+        let (quiz_sender, quiz_receiver) = MainContext::channel(Priority::default());
+        quiz_receiver.attach(None, clone!(@weak window, @weak document => @default-return Continue(false),
+            move |result: Result<(CacheDesc, Vec<AccessEvent>), String>| {
+                match result {
+                    Ok((cache, events)) => quiz::dialog::show(&window, cache, events),
+                    Err(message) => document.add_toast(&message),
+                }
+                Continue(false)
+            }
+        ));
+
+        thread::spawn(move || {
+            let result = run_sim(&path_buf, &options, true)
+                .map_err(|err| format!("Could not simulate {}: {}", path_buf.display(), err))
+                .and_then(|mut levels| {
+                    let level = levels.swap_remove(0);
+                    if level.events.is_empty() {
+                        return Err(format!("{} contains no accesses to ask", path_buf.display()));
+                    }
+                    Ok((level.cache, level.events))
+                });
+            quiz_sender.send(result).expect("Could not send through channel");
+        });
+    }));
+    window.add_action(&quiz_action);
+
+    // The buttons and actions follow the trace of the selected tab and whether it is simulated.
+    let update = Rc::new(clone!(@weak window, @weak simulate_button, @weak export_report_action, @weak quiz_action => move || {
+        let document = window.document();
+        let has_trace = document.as_ref().is_some_and(|document| document.path_buf().is_file());
+        let running = document.as_ref().is_some_and(CacheCacheDocument::running);
+        simulate_button.set_sensitive(has_trace && !running);
+        export_report_action.set_enabled(has_trace);
+        quiz_action.set_enabled(has_trace);
+    }));
+    update();
+
+    let selected: Rc<RefCell<Option<SelectedDocument>>> = Rc::default();
+    window.tab_view().connect_selected_page_notify(clone!(@weak window, @strong update, @strong selected => move |_| {
+        if let Some((document, handlers)) = selected.take() {
+            for handler in handlers {
+                document.disconnect(handler);
+            }
+        }
+        if let Some(document) = window.document() {
+            let handlers = vec![
+                document.connect_path_buf_notify(clone!(@strong update => move |_| update())),
+                document.connect_running_notify(clone!(@strong update => move |_| update())),
+            ];
+            selected.replace(Some((document, handlers)));
+        }
+        update();
+    }));
+
+    let menu = gio::Menu::new();
+    menu.append(Some("Export HTML Report…"), Some("win.export-report"));
+    menu.append(Some("Quiz…"), Some("win.quiz"));
+    let menu_button = MenuButton::builder()
+        .icon_name("open-menu-symbolic")
+        .tooltip_text("Main Menu")
+        .menu_model(&menu)
+        .build();
+
+    let overrides_button = window::overrides::button(
+        clone!(@weak window => @default-return None, move || window.document().map(|document| document.overrides())),
+        clone!(@weak window => move |overrides| {
+            if let Some(document) = window.document() {
+                document.set_overrides(overrides);
+            }
+        }),
+    );
+
     header_bar.pack_start(&open_file_button);
+    header_bar.pack_start(&new_tab_button);
+    header_bar.pack_start(&open_config_button);
+    header_bar.pack_start(&overrides_button);
+    header_bar.pack_start(&generate_button);
     header_bar.pack_start(&simulate_button);
+    header_bar.pack_end(&menu_button);
 
     window.set_titlebar(Some(&header_bar));
 
-    window.present();
-    0
+    window
 }
 
 type CacheLineVec = Vec<Vec<CacheEntry>>;
 
-fn run_sim(path: &PathBuf) -> Result<SimResult, Box<dyn Error>> {
-    let (cache, addrs) = sim::read(path)?;
+/// How to read a trace and which cache to simulate it with.
+#[derive(Clone, Debug, Default)]
+pub struct SimOptions {
+    pub strict: bool,
+    pub config_path: Option<PathBuf>,
+    /// Detected from the trace if not given.
+    pub format: Option<Format>,
+    /// Applied to the first level.
+    pub overrides: CacheOverrides,
+}
+
+/// Opens a trace and returns the levels to simulate it with and its accesses. The levels are
+/// taken from the configuration file if one is given, otherwise from the cache of the overrides
+/// or the trace header. The overrides are applied to the first level.
+fn open_trace(path: &Path, options: &SimOptions) -> Result<(Vec<Level>, Accesses), Box<dyn Error>> {
+    let config = options.config_path.as_deref().map(cli::load_config).transpose()?;
 
-    let (lines, stats) = sim::simulate(&cache, &addrs);
+    let strict = options.strict || config.as_ref().is_some_and(|config| config.strict);
+    let Trace { cache, accesses } = trace::open(path, options.format, strict)?;
+
+    // Without configuration, the trace has to describe the cache, so report what is wrong with it.
+    let mut levels = match (config, &options.overrides.cache) {
+        (Some(config), _) => config.levels,
+        (None, Some(cache)) => vec![Level { name: "Cache".to_string(), cache: cache.clone() }],
+        (None, None) => vec![Level { name: "Cache".to_string(), cache: cache? }],
+    };
+    levels[0].cache = options.overrides.apply(&levels[0].cache)?;
+
+    Ok((levels, accesses))
+}
+
+/// Reads and simulates a trace with the levels of `open_trace`.
+/// The path `-` reads the trace from stdin.
+/// The trace is streamed, so without `record_history` it is simulated in constant memory and
+/// every line only contains its final entry.
+fn run_sim(path: &Path, options: &SimOptions, record_history: bool) -> Result<SimResult, Box<dyn Error>> {
+    let (levels, accesses) = open_trace(path, options)?;
+
+    let offset_bits = levels[0].cache.offset_bits();
+    let caches: Vec<CacheDesc> = levels.iter().map(|level| level.cache.clone()).collect();
+    let mut hierarchy = Hierarchy::new(&caches, record_history);
+    for access in accesses {
+        for addr in access?.line_addrs(offset_bits) {
+            hierarchy.access(addr);
+        }
+    }
 
-    Ok((lines, cache, addrs, stats))
+    Ok(levels.into_iter()
+        .zip(hierarchy.finish())
+        .map(|(level, (lines, events, stats))| LevelResult { name: level.name, cache: level.cache, lines, events, stats })
+        .collect())
 }
@@ -0,0 +1,217 @@
+// A window that asks the accesses of a simulated trace one after another.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gtk::glib::{self, clone};
+use gtk::{prelude::*, Align, Button, DropDown, Entry, Grid, Label, Orientation, Window};
+use libadwaita::HeaderBar;
+
+use crate::sim::{AccessEvent, CacheDesc};
+
+use super::{Answer, Quiz};
+
+/// The widgets of the window that change from question to question.
+struct View {
+    question: Label,
+    set: Entry,
+    tag: Entry,
+    result: DropDown,
+    way: Entry,
+    feedback: Label,
+    score: Label,
+    check: Button,
+    next: Button,
+}
+
+/// Parses the text of `entry` as a number in `radix`, allowing a "0x" prefix for hexadecimal.
+fn parse_entry(entry: &Entry, radix: u32) -> Option<u64> {
+    let text = entry.text();
+    let text = text.trim();
+    let text = if radix == 16 { text.trim_start_matches("0x") } else { text };
+    u64::from_str_radix(text, radix).ok()
+}
+
+impl View {
+    /// Shows the current access of `quiz`, or the final score after the last one.
+    fn show_question(&self, quiz: &Quiz) {
+        let (score, asked) = quiz.score();
+        self.score.set_label(&format!("Score: {}/{}", score, asked));
+
+        let (position, len) = quiz.progress();
+        match quiz.current() {
+            Some(event) => {
+                self.question.set_label(&format!("Access {} of {}: address {:x}", position + 1, len, event.addr));
+                for entry in [&self.set, &self.tag, &self.way] {
+                    entry.set_text("");
+                    entry.set_sensitive(true);
+                }
+                self.result.set_sensitive(true);
+                self.feedback.set_visible(false);
+                self.check.set_sensitive(true);
+                self.next.set_sensitive(false);
+                self.set.grab_focus();
+            }
+            None => {
+                self.question.set_label(&format!("Finished all {} accesses with {} of {} points", len, score, asked));
+                for entry in [&self.set, &self.tag, &self.way] {
+                    entry.set_sensitive(false);
+                }
+                self.result.set_sensitive(false);
+                self.check.set_sensitive(false);
+                self.next.set_sensitive(false);
+            }
+        }
+    }
+
+    /// Checks the entered answer and reveals the solution of the current access.
+    fn check(&self, quiz: &mut Quiz) {
+        let Some(event) = quiz.current().cloned() else {
+            return;
+        };
+        let answer = Answer {
+            set: parse_entry(&self.set, 10),
+            tag: parse_entry(&self.tag, 16),
+            hit: self.result.selected() == 0,
+            way: parse_entry(&self.way, 10),
+        };
+        let set_before = quiz.describe_set(event.set);
+        let mistakes = quiz.check(&answer);
+
+        let correct = mistakes.is_empty();
+        let mut feedback = vec![describe(&event)];
+        if correct {
+            feedback.push("Everything is correct.".to_string());
+        } else {
+            feedback.push(format!("Set {} before the access: {}", event.set, set_before));
+            feedback.extend(mistakes);
+        }
+        self.feedback.set_label(&feedback.join("\n"));
+        self.feedback.set_css_classes(if correct { &["success"] } else { &["error"] });
+        self.feedback.set_visible(true);
+
+        let (score, asked) = quiz.score();
+        self.score.set_label(&format!("Score: {}/{}", score, asked));
+        for entry in [&self.set, &self.tag, &self.way] {
+            entry.set_sensitive(false);
+        }
+        self.result.set_sensitive(false);
+        self.check.set_sensitive(false);
+        self.next.set_sensitive(true);
+        self.next.grab_focus();
+    }
+}
+
+/// The solution of an access in a single line.
+fn describe(event: &AccessEvent) -> String {
+    let result = if event.hit { "hit" } else { "miss" };
+    let evicted = event.evicted.map_or(String::new(), |tag| format!(", evicts {:x}", tag));
+    format!("Set {}, tag {:x}: {} in way {}{}", event.set, event.tag, result, event.way, evicted)
+}
+
+/// Shows the quiz for the accesses of a cache level. They have to be recorded, i.e. not empty.
+pub fn show(parent: &impl IsA<Window>, cache: CacheDesc, events: Vec<AccessEvent>) {
+    let entry = |tooltip: &str| {
+        Entry::builder()
+            .tooltip_text(tooltip)
+            .hexpand(true)
+            .activates_default(true)
+            .build()
+    };
+
+    let view = View {
+        question: Label::builder().halign(Align::Start).build(),
+        set: entry("Set index in decimal"),
+        tag: entry("Tag in hexadecimal"),
+        result: DropDown::from_strings(&["Hit", "Miss"]),
+        way: entry("The way that hits or that is filled after the miss"),
+        feedback: Label::builder()
+            .wrap(true)
+            .halign(Align::Start)
+            .visible(false)
+            .build(),
+        score: Label::new(None),
+        check: Button::builder()
+            .label("Check")
+            .css_classes(["suggested-action"])
+            .build(),
+        next: Button::builder().label("Next").build(),
+    };
+
+    let cache_label = Label::builder()
+        .label(cache.to_string())
+        .wrap(true)
+        .halign(Align::Start)
+        .build();
+
+    let grid = Grid::builder()
+        .row_spacing(10)
+        .column_spacing(10)
+        .build();
+    let rows: [(&str, &gtk::Widget); 4] = [
+        ("Set", view.set.upcast_ref()),
+        ("Tag", view.tag.upcast_ref()),
+        ("Result", view.result.upcast_ref()),
+        ("Way", view.way.upcast_ref()),
+    ];
+    for (row, (title, widget)) in rows.into_iter().enumerate() {
+        let label = Label::builder().label(title).halign(Align::Start).build();
+        grid.attach(&label, 0, row as i32, 1, 1);
+        grid.attach(widget, 1, row as i32, 1, 1);
+    }
+
+    let buttons = gtk::Box::builder()
+        .spacing(10)
+        .halign(Align::End)
+        .build();
+    buttons.append(&view.check);
+    buttons.append(&view.next);
+
+    let container_box = gtk::Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(10)
+        .margin_top(10)
+        .margin_end(10)
+        .margin_start(10)
+        .margin_bottom(10)
+        .build();
+    container_box.append(&cache_label);
+    container_box.append(&view.question);
+    container_box.append(&grid);
+    container_box.append(&buttons);
+    container_box.append(&view.feedback);
+
+    let header_bar = HeaderBar::new();
+    header_bar.pack_end(&view.score);
+
+    let dialog = Window::builder()
+        .title("Quiz")
+        .transient_for(parent)
+        .default_width(450)
+        .child(&container_box)
+        .titlebar(&header_bar)
+        .build();
+
+    let quiz = Rc::new(RefCell::new(Quiz::new(cache, events)));
+    let view = Rc::new(view);
+    view.show_question(&quiz.borrow());
+
+    // The view owns the buttons, so their handlers only refer to it weakly. The dialog keeps the
+    // view until it is destroyed.
+    view.check.connect_clicked(clone!(@weak view, @strong quiz => move |_| {
+        view.check(&mut quiz.borrow_mut());
+    }));
+    view.next.connect_clicked(clone!(@weak view, @strong quiz => move |_| {
+        let mut quiz = quiz.borrow_mut();
+        quiz.advance();
+        view.show_question(&quiz);
+    }));
+    // Enter in an entry checks the answer.
+    dialog.set_default_widget(Some(&view.check));
+    let view = RefCell::new(Some(view));
+    dialog.connect_destroy(move |_| {
+        view.take();
+    });
+
+    dialog.present();
+}
@@ -0,0 +1,169 @@
+// A quiz that reveals a simulated trace one access at a time. For every access, the set index, the
+// tag, whether it hits and the way it uses (the hit way or the victim of a miss) are asked before
+// the answer of the simulation is shown. Every part that was answered correctly scores a point,
+// every mistake is explained with the state of the set before the access.
+
+use crate::sim::{AccessEvent, CacheDesc, Strategy};
+
+pub mod dialog;
+
+/// Number of parts asked per access.
+pub const PARTS: u32 = 4;
+
+/// What the student entered. Parts that could not be parsed are `None` and count as wrong.
+pub struct Answer {
+    pub set: Option<u64>,
+    pub tag: Option<u64>,
+    pub hit: bool,
+    pub way: Option<u64>,
+}
+
+/// The entry of a cache line, as far as the replacement strategies are concerned.
+#[derive(Clone, Copy)]
+struct Slot {
+    tag: u64,
+    last_used: u64,
+    count_used: u64,
+}
+
+pub struct Quiz {
+    cache: CacheDesc,
+    events: Vec<AccessEvent>,
+    /// The content of every line before the current access, replayed from the events.
+    lines: Vec<Option<Slot>>,
+    position: usize,
+    score: u32,
+    asked: u32,
+}
+
+impl Quiz {
+    pub fn new(cache: CacheDesc, events: Vec<AccessEvent>) -> Self {
+        Quiz {
+            lines: vec![None; (cache.n_sets() * cache.assoc()) as usize],
+            cache,
+            events,
+            position: 0,
+            score: 0,
+            asked: 0,
+        }
+    }
+
+    /// The access that is asked for, or `None` after the last one.
+    pub fn current(&self) -> Option<&AccessEvent> {
+        self.events.get(self.position)
+    }
+
+    /// Index of the current access and the number of accesses.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.position, self.events.len())
+    }
+
+    /// Points scored and points possible so far.
+    pub fn score(&self) -> (u32, u32) {
+        (self.score, self.asked)
+    }
+
+    fn set_lines(&self, set: u64) -> &[Option<Slot>] {
+        let first = (set * self.cache.assoc()) as usize;
+        &self.lines[first..first + self.cache.assoc() as usize]
+    }
+
+    /// Checks `answer` against the current access, adds it to the score and returns an explanation
+    /// for every mistake. An empty list means everything was right.
+    pub fn check(&mut self, answer: &Answer) -> Vec<String> {
+        let Some(event) = self.current().cloned() else {
+            return Vec::new();
+        };
+        let offset_bits = self.cache.offset_bits();
+        let idx_bits = self.cache.idx_bits();
+        let mut mistakes = Vec::new();
+
+        if answer.set != Some(event.set) {
+            mistakes.push(format!(
+                "Set: The {} bits after the {} offset bits are the set index: ({:x} >> {}) mod {} = {}",
+                idx_bits, offset_bits, event.addr, offset_bits, self.cache.n_sets(), event.set
+            ));
+        }
+        if answer.tag != Some(event.tag) {
+            mistakes.push(format!(
+                "Tag: The bits above the {} offset and index bits are the tag: {:x} >> {} = {:x}",
+                offset_bits + idx_bits, event.addr, offset_bits + idx_bits, event.tag
+            ));
+        }
+        if answer.hit != event.hit {
+            mistakes.push(if event.hit {
+                format!("Result: Way {} of set {} already held tag {:x}, so the access hits", event.way, event.set, event.tag)
+            } else {
+                format!("Result: No way of set {} held tag {:x}, so the access misses", event.set, event.tag)
+            });
+        }
+        if answer.way != Some(event.way) {
+            mistakes.push(format!("Way: {}", self.explain_way(&event)));
+        }
+
+        self.asked += PARTS;
+        self.score += PARTS - mistakes.len() as u32;
+        mistakes
+    }
+
+    /// Why the access used its way, based on the content of the set before it.
+    fn explain_way(&self, event: &AccessEvent) -> String {
+        if event.hit {
+            return format!("Tag {:x} was found in way {}", event.tag, event.way);
+        }
+
+        let set = self.set_lines(event.set);
+        if set.iter().any(Option::is_none) && self.cache.strategy() != Strategy::First {
+            return format!("Way {} is the first empty way of set {}", event.way, event.set);
+        }
+        let victim = set[event.way as usize];
+        match (self.cache.strategy(), victim) {
+            (Strategy::LRU, Some(victim)) => format!(
+                "The set was full and LRU replaces the entry used least recently: tag {:x} in way {}, last used at time {}",
+                victim.tag, event.way, victim.last_used
+            ),
+            (Strategy::LFU, Some(victim)) => format!(
+                "The set was full and LFU replaces the entry used least often: tag {:x} in way {}, used {} times",
+                victim.tag, event.way, victim.count_used
+            ),
+            _ => "First always replaces way 0".to_string(),
+        }
+    }
+
+    /// The tags in the ways of `set` before the current access, "-" for empty ways.
+    pub fn describe_set(&self, set: u64) -> String {
+        let ways: Vec<String> = self
+            .set_lines(set)
+            .iter()
+            .enumerate()
+            .map(|(way, slot)| match slot {
+                Some(slot) => format!("way {}: {:x}", way, slot.tag),
+                None => format!("way {}: -", way),
+            })
+            .collect();
+        ways.join(", ")
+    }
+
+    /// Applies the current access to the replayed cache and moves on to the next one.
+    pub fn advance(&mut self) {
+        let Some(event) = self.current().cloned() else {
+            return;
+        };
+        let index = (event.set * self.cache.assoc() + event.way) as usize;
+        let slot = &mut self.lines[index];
+        match slot {
+            Some(slot) if event.hit => {
+                slot.last_used = event.time;
+                slot.count_used += 1;
+            }
+            _ => {
+                *slot = Some(Slot {
+                    tag: event.tag,
+                    last_used: event.time,
+                    count_used: 1,
+                })
+            }
+        }
+        self.position += 1;
+    }
+}
@@ -0,0 +1,373 @@
+// An interactive session with a single cache. Every input line is either a command or accesses:
+//
+//     1a0 1b0 0x2a0          hexadecimal addresses, one access each
+//     100..200 step 16       every address from 100 up to 200, excluding it, 16 bytes apart
+//     pattern zipf --count 8 a synthetic pattern with the options of `cachecache generate`
+//
+// Each input is simulated at once. For short inputs every access is printed with whether it hit,
+// its set and way and the content of the set afterwards, longer ones only print a summary. An
+// input is a single step for `undo`, which simulates the remaining accesses again from the start.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufWriter, Write};
+
+use crate::cli::{self, Args};
+use crate::generate;
+use crate::sim::{self, AccessEvent, Cache, CacheDesc, Strategy};
+
+/// Inputs with more accesses only print a summary.
+const VERBOSE_LIMIT: usize = 16;
+
+/// Inputs with more accesses are rejected, so a typo cannot exhaust the memory.
+const MAX_ACCESSES: usize = 1 << 24;
+
+const HELP: &str = "\
+ADDRESS...              Access hexadecimal addresses, e.g. 1a0 or 0x1a0
+START..END [step N]     Access every N bytes from START up to END, excluding it.
+                        N defaults to the line size
+pattern NAME [OPTIONS]  Access a synthetic pattern, see \"cachecache help generate\"
+show [SET]              Print the content of every set or of SET
+stats                   Print the number of hits, misses and evictions
+undo [N]                Take back the last N inputs, 1 by default
+reset                   Take back every input
+save FILE               Write the accesses as a trace with the cache as header
+help                    Print this
+quit                    End the session, like the end of the input";
+
+pub struct Repl {
+    desc: CacheDesc,
+    cache: Cache,
+    addrs: Vec<u64>,
+    /// Number of accesses of every input, to take them back together.
+    steps: Vec<usize>,
+}
+
+/// Parses a hexadecimal address, optionally prefixed with `0x`.
+fn parse_hex(text: &str) -> Result<u64, String> {
+    let hex = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")).unwrap_or(text);
+    u64::from_str_radix(hex, 16).map_err(|_| format!("Invalid hexadecimal address \"{}\"", text))
+}
+
+impl Repl {
+    pub fn new(desc: CacheDesc) -> Self {
+        Repl {
+            cache: Cache::new(&desc, false),
+            desc,
+            addrs: Vec::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Simulates `addrs` as one input and returns the lines to print.
+    pub fn access(&mut self, addrs: Vec<u64>) -> Vec<String> {
+        let verbose = addrs.len() <= VERBOSE_LIMIT;
+        let mut output = Vec::new();
+        let (mut hits, mut evictions) = (0, 0);
+
+        for &addr in &addrs {
+            let event = self.cache.access_event(addr);
+            hits += usize::from(event.hit);
+            evictions += usize::from(event.evicted.is_some());
+            if verbose {
+                output.push(self.describe_access(&event));
+                output.push(format!("    set {}: {}", event.set, self.describe_set(event.set)));
+            }
+        }
+        if !verbose {
+            output.push(format!(
+                "{} accesses: {} hits, {} misses, {} evictions",
+                addrs.len(), hits, addrs.len() - hits, evictions
+            ));
+        }
+
+        self.steps.push(addrs.len());
+        self.addrs.extend(addrs);
+        output
+    }
+
+    fn describe_access(&self, event: &AccessEvent) -> String {
+        let result = if event.hit { "hit" } else { "miss" };
+        let evicted = event.evicted.map_or(String::new(), |tag| format!(", evicted tag {:x}", tag));
+        format!(
+            "{:x}: {}, tag {:x}, set {}, way {}{}",
+            event.addr, result, event.tag, event.set, event.way, evicted
+        )
+    }
+
+    /// The tags in the ways of `set`, with what the replacement strategy looks at.
+    fn describe_set(&self, set: u64) -> String {
+        let ways: Vec<String> = self
+            .cache
+            .set(set)
+            .iter()
+            .enumerate()
+            .map(|(way, entry)| match (entry, self.desc.strategy()) {
+                (None, _) => format!("way {}: -", way),
+                (Some(entry), Strategy::LRU) => {
+                    format!("way {}: {:x} (last used at {})", way, entry.tag(), entry.last_used())
+                }
+                (Some(entry), Strategy::LFU) => {
+                    format!("way {}: {:x} (used {} times)", way, entry.tag(), entry.count_used())
+                }
+                (Some(entry), Strategy::First) => format!("way {}: {:x}", way, entry.tag()),
+            })
+            .collect();
+        ways.join(", ")
+    }
+
+    fn stats(&self) -> String {
+        let stats = self.cache.stats();
+        let hit_rate = if stats.accesses() == 0 { 0.0 } else { stats.hits() as f64 / stats.accesses() as f64 };
+        format!(
+            "Accesses: {}. Hits: {}. Misses: {}. Evictions: {}. Hit rate: {:.1}%",
+            stats.accesses(), stats.hits(), stats.misses(), stats.evictions(), hit_rate * 100.0
+        )
+    }
+
+    /// Takes back the last `n` inputs and returns the number of accesses they had.
+    fn undo(&mut self, n: usize) -> usize {
+        let n = n.min(self.steps.len());
+        let taken: usize = self.steps.drain(self.steps.len() - n..).sum();
+        self.addrs.truncate(self.addrs.len() - taken);
+
+        // Entries do not remember what they replaced, so the rest is simulated again.
+        self.cache = Cache::new(&self.desc, false);
+        for &addr in &self.addrs {
+            self.cache.access(addr);
+        }
+        taken
+    }
+
+    /// The addresses of `START..END [step N]`.
+    fn range(&self, range: &str, words: &[&str]) -> Result<Vec<u64>, String> {
+        let (start, end) = range.split_once("..").ok_or("Expected a range like 100..200")?;
+        let (start, end) = (parse_hex(start)?, parse_hex(end)?);
+        let step = match words {
+            [] => 1 << self.desc.offset_bits(),
+            ["step", step] => generate::parse_address(step)
+                .filter(|&step| step > 0)
+                .ok_or_else(|| format!("Invalid step \"{}\", expected a positive number", step))?,
+            _ => return Err("Expected a range like 100..200 step 16".to_string()),
+        };
+
+        let count = end.saturating_sub(start).div_ceil(step);
+        if count > MAX_ACCESSES as u64 {
+            return Err(format!("The range has more than {} accesses", MAX_ACCESSES));
+        }
+        Ok((0..count).map(|i| start + i * step).collect())
+    }
+
+    /// The addresses of `pattern NAME [OPTIONS]`.
+    fn pattern(words: &[&str]) -> Result<Vec<u64>, String> {
+        let mut args = Args::new(words.iter().map(|word| word.to_string()).collect());
+        let mut workload = cli::workload_options(&mut args).map_err(|err| err.to_string())?;
+        let positional = args.finish(&["PATTERN"], 1).map_err(|err| err.to_string())?;
+        workload.pattern = positional[0].parse::<generate::Pattern>().map_err(|err| err.to_string())?;
+
+        let addrs: Vec<u64> = workload.addrs().map_err(|err| err.to_string())?.take(MAX_ACCESSES + 1).collect();
+        if addrs.len() > MAX_ACCESSES {
+            return Err(format!("The pattern has more than {} accesses", MAX_ACCESSES));
+        }
+        Ok(addrs)
+    }
+
+    /// Runs one input line and returns the lines to print, or `None` to end the session.
+    fn execute(&mut self, line: &str) -> Result<Option<Vec<String>>, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let output = match words[..] {
+            [] => Vec::new(),
+            ["quit" | "exit"] => return Ok(None),
+            ["help"] => HELP.lines().map(str::to_string).collect(),
+            ["stats"] => vec![self.stats()],
+            ["show"] => (0..self.desc.n_sets())
+                .map(|set| format!("set {}: {}", set, self.describe_set(set)))
+                .collect(),
+            ["show", set] => {
+                let set = set.parse().ok().filter(|&set| set < self.desc.n_sets()).ok_or_else(|| {
+                    format!("Invalid set \"{}\", the cache has {} sets", set, self.desc.n_sets())
+                })?;
+                vec![format!("set {}: {}", set, self.describe_set(set))]
+            }
+            ["undo"] | ["undo", _] => {
+                let n = match words.get(1) {
+                    Some(n) => n.parse().map_err(|_| format!("Invalid number of inputs \"{}\"", n))?,
+                    None => 1,
+                };
+                let taken = self.undo(n);
+                vec![format!("Took back {} accesses", taken), self.stats()]
+            }
+            ["reset"] => {
+                self.undo(self.steps.len());
+                vec!["The cache is empty".to_string()]
+            }
+            ["save", path] => {
+                File::create(path)
+                    .and_then(|file| sim::write(&mut BufWriter::new(file), Some(&self.desc), self.addrs.iter().copied()))
+                    .map_err(|err| format!("Could not write {}: {}", path, err))?;
+                vec![format!("Saved {} accesses to {}", self.addrs.len(), path)]
+            }
+            ["pattern", ref rest @ ..] => {
+                let addrs = Self::pattern(rest)?;
+                self.access(addrs)
+            }
+            [first, ref rest @ ..] if first.contains("..") => {
+                let addrs = self.range(first, rest)?;
+                self.access(addrs)
+            }
+            _ => {
+                let addrs = words.iter().map(|word| parse_hex(word)).collect::<Result<_, _>>().map_err(|err| {
+                    format!("{}. Type \"help\" for the commands", err)
+                })?;
+                self.access(addrs)
+            }
+        };
+        Ok(Some(output))
+    }
+
+    /// Reads inputs until `quit` or the end of `input`. Interactive sessions print a prompt and
+    /// report mistakes on stderr, others stop at the first one.
+    pub fn run(&mut self, input: impl BufRead, output: &mut impl Write, interactive: bool) -> Result<(), Box<dyn Error>> {
+        writeln!(
+            output,
+            "Cache: {} (sets: {}, tag bits: {}, index bits: {}, offset bits: {})",
+            self.desc.spec(), self.desc.n_sets(), self.desc.tag_bits(), self.desc.idx_bits(), self.desc.offset_bits()
+        )?;
+        if !self.addrs.is_empty() {
+            writeln!(output, "{}", self.stats())?;
+        }
+        if interactive {
+            writeln!(output, "Type \"help\" for the commands.")?;
+        }
+
+        let mut lines = input.lines().enumerate();
+        loop {
+            if interactive {
+                write!(output, "> ")?;
+                output.flush()?;
+            }
+            let Some((number, line)) = lines.next() else {
+                if interactive {
+                    writeln!(output)?;
+                }
+                break;
+            };
+            // Everything after `#` is a comment, so sessions can be scripted.
+            let line = line?;
+            let line = line.split('#').next().unwrap_or_default();
+
+            match self.execute(line) {
+                Ok(Some(lines)) => {
+                    for line in lines {
+                        writeln!(output, "{}", line)?;
+                    }
+                }
+                Ok(None) => break,
+                Err(message) if interactive => eprintln!("{}", message),
+                Err(message) => return Err(format!("Line {}: {}", number + 1, message).into()),
+            }
+        }
+
+        output.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the non-interactive session `input` with a cache of 8 sets of 2 ways and 16 byte lines
+    /// and returns the lines it printed after the cache.
+    fn session(input: &str) -> Result<Vec<String>, String> {
+        let mut repl = Repl::new(CacheDesc::from_spec("256B, 16B line, 2-way, LRU").unwrap());
+        let mut output = Vec::new();
+        repl.run(input.as_bytes(), &mut output, false).map_err(|err| err.to_string())?;
+        let output = String::from_utf8(output).unwrap();
+        Ok(output.lines().skip(1).map(str::to_string).collect())
+    }
+
+    /// The output of `input` without the content of the sets after accesses.
+    fn accesses(input: &str) -> Vec<String> {
+        session(input).unwrap().into_iter().filter(|line| !line.starts_with(' ')).collect()
+    }
+
+    #[test]
+    fn addresses() {
+        assert_eq!(
+            session("1a0 0x2a0 # two accesses").unwrap(),
+            [
+                "1a0: miss, tag 3, set 2, way 0",
+                "    set 2: way 0: 3 (last used at 0), way 1: -",
+                "2a0: miss, tag 5, set 2, way 1",
+                "    set 2: way 0: 3 (last used at 0), way 1: 5 (last used at 1)",
+            ]
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(
+            accesses("100..140 step 16"),
+            [
+                "100: miss, tag 2, set 0, way 0",
+                "110: miss, tag 2, set 1, way 0",
+                "120: miss, tag 2, set 2, way 0",
+                "130: miss, tag 2, set 3, way 0",
+            ]
+        );
+        // The step defaults to the line size, the end is excluded.
+        assert_eq!(
+            accesses("0..21"),
+            ["0: miss, tag 0, set 0, way 0", "10: miss, tag 0, set 1, way 0", "20: miss, tag 0, set 2, way 0"]
+        );
+        assert_eq!(accesses("0..30 step 0x20"), ["0: miss, tag 0, set 0, way 0", "20: miss, tag 0, set 2, way 0"]);
+        assert!(accesses("200..100").is_empty());
+
+        assert_eq!(session("0..30 step 0"), Err("Line 1: Invalid step \"0\", expected a positive number".to_string()));
+        assert_eq!(session("0..30 stride 4"), Err("Line 1: Expected a range like 100..200 step 16".to_string()));
+        assert_eq!(session("0..zz"), Err("Line 1: Invalid hexadecimal address \"zz\"".to_string()));
+    }
+
+    #[test]
+    fn undo_replays_the_rest() {
+        let output = session("1a0\n2a0 3a0\n1a0\nundo 2\nstats").unwrap();
+        let undone = output.iter().position(|line| line.starts_with("Took back")).unwrap();
+        assert_eq!(output[undone], "Took back 3 accesses");
+        // Only the first input is simulated again, although 3a0 evicted it.
+        assert_eq!(output[undone + 1..], ["Accesses: 1. Hits: 0. Misses: 1. Evictions: 0. Hit rate: 0.0%"; 2]);
+        assert_eq!(accesses("1a0\n2a0 3a0\nundo\n1a0").last().unwrap(), "1a0: hit, tag 3, set 2, way 0");
+
+        // Taking back more inputs than there are empties the cache.
+        assert_eq!(session("1a0\nundo 5").unwrap()[2], "Took back 1 accesses");
+        assert_eq!(session("undo x"), Err("Line 1: Invalid number of inputs \"x\"".to_string()));
+    }
+
+    #[test]
+    fn reset() {
+        assert_eq!(
+            session("1a0 2a0\nreset\nstats").unwrap()[4..],
+            ["The cache is empty", "Accesses: 0. Hits: 0. Misses: 0. Evictions: 0. Hit rate: 0.0%"]
+        );
+        assert_eq!(accesses("1a0\nreset\n1a0").last().unwrap(), "1a0: miss, tag 3, set 2, way 0");
+    }
+
+    #[test]
+    fn show() {
+        assert_eq!(session("show").unwrap().len(), 8);
+        assert_eq!(session("1a0\nshow 2").unwrap()[2], "set 2: way 0: 3 (last used at 0), way 1: -");
+        assert_eq!(session("show 7").unwrap(), ["set 7: way 0: -, way 1: -"]);
+        assert_eq!(session("show 8"), Err("Line 1: Invalid set \"8\", the cache has 8 sets".to_string()));
+        assert_eq!(session("show -1"), Err("Line 1: Invalid set \"-1\", the cache has 8 sets".to_string()));
+    }
+
+    #[test]
+    fn stops_at_the_first_mistake() {
+        assert_eq!(
+            session("1a0\n\nzz\n1a0"),
+            Err("Line 3: Invalid hexadecimal address \"zz\". Type \"help\" for the commands".to_string())
+        );
+        // Nothing after quit is read.
+        assert_eq!(session("quit\nzz").unwrap(), Vec::<String>::new());
+    }
+}
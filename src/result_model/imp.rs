@@ -0,0 +1,51 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use gtk::gio;
+use gtk::glib::{self, BoxedAnyObject};
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+use crate::CacheLineVec;
+use super::LineRow;
+
+#[derive(Default)]
+pub struct ResultModel {
+    pub lines: RefCell<Rc<CacheLineVec>>,
+    pub assoc: Cell<u64>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for ResultModel {
+    const NAME: &'static str = "CacheCacheResultModel";
+    type Type = super::ResultModel;
+    type Interfaces = (gio::ListModel,);
+}
+
+impl ObjectImpl for ResultModel {}
+
+impl ListModelImpl for ResultModel {
+    fn item_type(&self) -> glib::Type {
+        BoxedAnyObject::static_type()
+    }
+
+    fn n_items(&self) -> u32 {
+        self.lines.borrow().len().try_into().unwrap_or(u32::MAX)
+    }
+
+    fn item(&self, position: u32) -> Option<glib::Object> {
+        // Rows are only created when the view asks for them, so a cache with a lot of lines
+        // does not need a widget or an object per line up front.
+        let lines = self.lines.borrow();
+        if position as usize >= lines.len() {
+            return None;
+        }
+
+        let row = LineRow {
+            lines: lines.clone(),
+            index: position as usize,
+            assoc: self.assoc.get(),
+        };
+        Some(BoxedAnyObject::new(row).upcast())
+    }
+}
@@ -0,0 +1,84 @@
+mod imp;
+
+use std::rc::Rc;
+
+use gtk::{glib, gio};
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use glib::Object;
+
+use crate::CacheLineVec;
+use crate::sim::CacheEntry;
+
+/// Number of previous entries of a line that are shown in the history column.
+const HISTORY_LIMIT: usize = 32;
+
+glib::wrapper! {
+    pub struct ResultModel(ObjectSubclass<imp::ResultModel>)
+        @implements gio::ListModel;
+}
+
+impl ResultModel {
+    pub fn new() -> Self {
+        Object::builder().build()
+    }
+
+    /// Replaces the displayed simulation result. Each cache line becomes one row.
+    pub fn set_result(&self, lines: Rc<CacheLineVec>, assoc: u64) {
+        let imp = self.imp();
+        let removed = imp.n_items();
+
+        imp.assoc.set(assoc);
+        imp.lines.replace(lines);
+
+        self.items_changed(0, removed, imp.n_items());
+    }
+
+    pub fn assoc(&self) -> u64 {
+        self.imp().assoc.get()
+    }
+}
+
+impl Default for ResultModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A single cache line of a simulation result as handed out by `ResultModel`.
+pub struct LineRow {
+    lines: Rc<CacheLineVec>,
+    index: usize,
+    assoc: u64,
+}
+
+impl LineRow {
+    pub fn set(&self) -> u64 {
+        self.index as u64 / self.assoc
+    }
+
+    pub fn way(&self) -> u64 {
+        self.index as u64 % self.assoc
+    }
+
+    pub fn current(&self) -> Option<&CacheEntry> {
+        self.lines[self.index].last()
+    }
+
+    /// Formats the entries that were evicted from this line, most recent last.
+    /// Only the last `HISTORY_LIMIT` entries are formatted so long traces stay cheap to scroll.
+    pub fn history(&self) -> String {
+        let line = &self.lines[self.index];
+        let previous = &line[..line.len().saturating_sub(1)];
+        let shown = &previous[previous.len().saturating_sub(HISTORY_LIMIT)..];
+
+        let mut history = String::new();
+        if shown.len() < previous.len() {
+            history.push_str(&format!("… {} earlier |", previous.len() - shown.len()));
+        }
+        for entry in shown {
+            history.push_str(&format!(" {:x} ({}) |", entry.tag(), entry.entered()));
+        }
+        history
+    }
+}
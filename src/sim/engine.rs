@@ -0,0 +1,300 @@
+// Simulation of one access at a time, so traces can be streamed instead of loaded into memory.
+
+use super::{AccessEvent, CacheDesc, CacheEntry, CacheStats, Strategy};
+
+/// A cache being simulated. Only the current entry of every line and the statistics are kept,
+/// so traces of any length are simulated in constant memory. Optionally, every entry a line ever
+/// held and every access are recorded as well.
+pub struct Cache {
+    desc: CacheDesc,
+    idx_mask: u64,
+    tag_mask: u64,
+    /// The current entry of every line, set by set.
+    lines: Vec<Option<CacheEntry>>,
+    /// Every entry of every line, oldest first, if recording was requested.
+    history: Option<Vec<Vec<CacheEntry>>>,
+    /// Every access, if recording was requested.
+    events: Option<Vec<AccessEvent>>,
+    stats: CacheStats,
+    /// Number of accesses so far, which is the time of the next access.
+    time: u64,
+}
+
+impl Cache {
+    pub fn new(desc: &CacheDesc, record_history: bool) -> Self {
+        let n_blocks: usize = desc
+            .n_blocks
+            .try_into()
+            .expect("Block count too large for 32 bit machine.");
+
+        // Build masks to split address into parts.
+        // Example:
+        // Block Count = 16, Block Size = 16, Associativity = 4, (=> 4 Sets)
+        // addr = 100110011001
+        //        ttttttiioooo
+        // ( o = offset, i = set index, t = tag )
+        // The masks will have a one bit in the corresponding places above.
+
+        let mut idx_mask: u64 = 0;
+        for j in desc.block_size..(desc.block_size + desc.idx_bits()) {
+            idx_mask |= 1 << j;
+        }
+
+        let mut tag_mask: u64 = 0;
+        for j in (desc.addr_size - desc.tag_bits())..desc.addr_size {
+            tag_mask |= 1 << j;
+        }
+
+        Cache {
+            desc: desc.clone(),
+            idx_mask,
+            tag_mask,
+            lines: vec![None; n_blocks],
+            history: record_history.then(|| vec![vec![]; n_blocks]),
+            events: record_history.then(Vec::new),
+            stats: CacheStats {
+                hits: 0,
+                misses: 0,
+                evictions: 0,
+            },
+            time: 0,
+        }
+    }
+
+    /// Simulates an access to `addr` and returns whether it hit.
+    pub fn access(&mut self, addr: u64) -> bool {
+        self.access_event(addr).hit
+    }
+
+    /// Simulates an access to `addr` and returns what happened.
+    pub fn access_event(&mut self, addr: u64) -> AccessEvent {
+        let time = self.time;
+        self.time += 1;
+
+        // The tag is the leftmost part of the address and needs to be shifted by the length of the
+        // tail.
+        let tag = (addr & self.tag_mask) >> (self.desc.block_size + self.desc.idx_bits());
+
+        // The set index is to the left of the block size.
+        let set_idx = (addr & self.idx_mask) >> self.desc.block_size;
+        let offset = addr & !(u64::MAX << self.desc.block_size);
+        let first = (set_idx * self.desc.assoc) as usize;
+        let set = first..first + self.desc.assoc as usize;
+
+        // Hit! Entry in the set with matching tag was found.
+        let hit = self.lines[set.clone()]
+            .iter()
+            .position(|entry| entry.as_ref().is_some_and(|entry| entry.tag == tag));
+        let mut event = AccessEvent {
+            time,
+            addr,
+            tag,
+            set: set_idx,
+            offset,
+            hit: hit.is_some(),
+            way: 0,
+            evicted: None,
+        };
+
+        if let Some(way) = hit {
+            let entry = self.lines[first + way].as_mut().expect("Only full lines can hit.");
+            entry.count_used += 1;
+            entry.last_used = time;
+
+            // The last recorded entry is the current one.
+            if let Some(history) = &mut self.history {
+                history[first + way].pop();
+                history[first + way].push(entry.clone());
+            }
+
+            event.way = way as u64;
+            if let Some(events) = &mut self.events {
+                events.push(event.clone());
+            }
+
+            self.stats.hits += 1;
+            return event;
+        }
+
+        self.stats.misses += 1;
+
+        let way = self.victim(&self.lines[set]);
+        let index = first + way;
+        if let Some(evicted) = &self.lines[index] {
+            event.evicted = Some(evicted.tag);
+            self.stats.evictions += 1;
+        }
+        event.way = way as u64;
+        if let Some(events) = &mut self.events {
+            events.push(event.clone());
+        }
+
+        let new_entry = CacheEntry {
+            tag,
+            count_used: 1,
+            last_used: time,
+            entered: time,
+        };
+        if let Some(history) = &mut self.history {
+            history[index].push(new_entry.clone());
+        }
+        self.lines[index] = Some(new_entry);
+
+        event
+    }
+
+    /// Chooses the way of `set` that receives a missed entry.
+    fn victim(&self, set: &[Option<CacheEntry>]) -> usize {
+        let free = set.iter().position(Option::is_none);
+        // No empty line found. Evict the entry where the key is minimal.
+        let evict = |key: fn(&CacheEntry) -> u64| {
+            set.iter()
+                .enumerate()
+                .min_by_key(|(_, entry)| entry.as_ref().map(key))
+                .map(|(way, _)| way)
+                .expect("Set must contain at least an empty or a full line.")
+        };
+
+        match self.desc.strat {
+            Strategy::LRU => free.unwrap_or_else(|| evict(|entry| entry.last_used)),
+            Strategy::LFU => free.unwrap_or_else(|| evict(|entry| entry.count_used)),
+            // This trivial strategy should usually only be used with a direct (assoc = 1) cache.
+            Strategy::First => 0,
+        }
+    }
+
+    /// The current entries of the ways of `set`, `None` for empty ways.
+    pub fn set(&self, set: u64) -> &[Option<CacheEntry>] {
+        let first = (set * self.desc.assoc) as usize;
+        &self.lines[first..first + self.desc.assoc as usize]
+    }
+
+    pub fn stats(&self) -> &CacheStats {
+        &self.stats
+    }
+
+    /// Returns the recorded entries of every line and the recorded accesses together with the
+    /// statistics. Without recorded history, every line only contains its current entry, if any,
+    /// and no accesses are returned.
+    pub fn finish(self) -> (Vec<Vec<CacheEntry>>, Vec<AccessEvent>, CacheStats) {
+        let lines = match self.history {
+            Some(history) => history,
+            None => self.lines.into_iter().map(|entry| entry.into_iter().collect()).collect(),
+        };
+
+        (lines, self.events.unwrap_or_default(), self.stats)
+    }
+}
+
+/// A hierarchy of caches being simulated, first level first. Each level only sees the accesses
+/// that missed in the level before it.
+pub struct Hierarchy {
+    levels: Vec<Cache>,
+}
+
+impl Hierarchy {
+    pub fn new(levels: &[CacheDesc], record_history: bool) -> Self {
+        Hierarchy {
+            levels: levels
+                .iter()
+                .map(|desc| Cache::new(desc, record_history))
+                .collect(),
+        }
+    }
+
+    /// Simulates an access to `addr` and returns the index of the level that hit, or `None` if it
+    /// missed in every level.
+    pub fn access(&mut self, addr: u64) -> Option<usize> {
+        self.levels.iter_mut().position(|level| level.access(addr))
+    }
+
+    /// Returns the lines, accesses and statistics of every level, see `Cache::finish`.
+    pub fn finish(self) -> Vec<(Vec<Vec<CacheEntry>>, Vec<AccessEvent>, CacheStats)> {
+        self.levels.into_iter().map(Cache::finish).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sim::read;
+
+    use super::*;
+
+    /// A single set of two ways with lines of one byte, so addresses are tags.
+    fn two_ways(strat: Strategy) -> Cache {
+        Cache::new(&CacheDesc::new(8, 0, 2, 2, strat).unwrap(), true)
+    }
+
+    fn tags(set: &[Option<CacheEntry>]) -> Vec<Option<u64>> {
+        set.iter().map(|entry| entry.as_ref().map(CacheEntry::tag)).collect()
+    }
+
+    #[test]
+    fn lru_evicts_least_recently_used() {
+        let mut cache = two_ways(Strategy::LRU);
+        for addr in [1, 1, 2] {
+            cache.access(addr);
+        }
+        let event = cache.access_event(3);
+        assert!(!event.hit);
+        assert_eq!((event.way, event.evicted), (0, Some(1)));
+        assert_eq!(tags(cache.set(0)), [Some(3), Some(2)]);
+    }
+
+    #[test]
+    fn lfu_evicts_least_frequently_used() {
+        let mut cache = two_ways(Strategy::LFU);
+        for addr in [1, 1, 2] {
+            cache.access(addr);
+        }
+        let event = cache.access_event(3);
+        assert_eq!((event.way, event.evicted), (1, Some(2)));
+        assert_eq!(tags(cache.set(0)), [Some(1), Some(3)]);
+    }
+
+    #[test]
+    fn first_always_replaces_the_first_way() {
+        let mut cache = two_ways(Strategy::First);
+        cache.access(1);
+        let event = cache.access_event(2);
+        assert_eq!((event.way, event.evicted), (0, Some(1)));
+        assert_eq!(tags(cache.set(0)), [Some(2), None]);
+        assert!(!cache.access(1));
+    }
+
+    #[test]
+    fn hierarchy_passes_misses_on() {
+        let levels = [
+            CacheDesc::new(8, 0, 1, 1, Strategy::LRU).unwrap(),
+            CacheDesc::new(8, 0, 4, 4, Strategy::LRU).unwrap(),
+        ];
+        let mut hierarchy = Hierarchy::new(&levels, true);
+        let hits: Vec<_> = [1, 1, 2, 1, 3, 3].into_iter().map(|addr| hierarchy.access(addr)).collect();
+        assert_eq!(hits, [None, Some(0), None, Some(1), None, Some(0)]);
+
+        let results = hierarchy.finish();
+        let (_, first, first_stats) = &results[0];
+        let (_, second, second_stats) = &results[1];
+        let missed: Vec<_> = first.iter().filter(|event| !event.hit).map(|event| event.addr).collect();
+        assert_eq!(second.iter().map(|event| event.addr).collect::<Vec<_>>(), missed);
+        assert_eq!(second_stats.accesses(), first_stats.misses());
+        assert_eq!((second_stats.hits(), second_stats.misses()), (1, 3));
+    }
+
+    #[test]
+    fn evict_trace() {
+        let (desc, addrs) = read(include_str!("../../test/evict").as_bytes(), true).unwrap();
+        let mut cache = Cache::new(&desc.unwrap(), true);
+        let events: Vec<_> = addrs.map(|addr| cache.access_event(addr.unwrap())).collect();
+
+        let misses: Vec<_> = events.iter().filter(|event| !event.hit).map(|event| event.addr).collect();
+        assert_eq!(misses, [0xabe, 0xaca, 0xbca, 0xcca, 0xdca, 0xeca]);
+        let eca = &events[8];
+        assert_eq!((eca.set, eca.way, eca.evicted), (0, 0, Some(0x2b)));
+
+        assert_eq!(tags(cache.set(0)), [Some(0x3b), Some(0x2f), Some(0x33), Some(0x37)]);
+        assert_eq!(tags(cache.set(3)), [Some(0x2a), None, None, None]);
+        let stats = cache.stats();
+        assert_eq!((stats.hits(), stats.misses(), stats.evictions()), (5, 6, 1));
+    }
+}
@@ -0,0 +1,617 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::io::{BufRead, Write};
+use std::iter::{Enumerate, Peekable};
+use std::str::FromStr;
+use std::{fmt, io, vec};
+
+mod engine;
+mod spec;
+
+pub use engine::{Cache, Hierarchy};
+pub use spec::{format_size, parse_assoc, parse_size, SpecError, DEFAULT_ADDR_SIZE};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    LRU,
+    LFU,
+    First
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 3] = [Strategy::LRU, Strategy::LFU, Strategy::First];
+}
+
+impl FromStr for Strategy {
+    type Err = ParseStrategyError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "LFU" => Ok(Strategy::LFU),
+            "LRU" => Ok(Strategy::LRU),
+            "First" => Ok(Strategy::First),
+            _ => Err(ParseStrategyError),
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strategy::LRU => write!(f, "LRU"),
+            Strategy::LFU => write!(f, "LFU"),
+            Strategy::First => write!(f, "First"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseStrategyError;
+impl fmt::Display for ParseStrategyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid strategy")
+    }
+}
+impl Error for ParseStrategyError {
+}
+
+/// The header fields of a trace file in the order in which they appear.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeaderField {
+    AddrSize,
+    BlockSize,
+    BlockCount,
+    Assoc,
+    Strategy,
+}
+
+impl fmt::Display for HeaderField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderField::AddrSize => write!(f, "address size in bits"),
+            HeaderField::BlockSize => write!(f, "block size in bits"),
+            HeaderField::BlockCount => write!(f, "block count"),
+            HeaderField::Assoc => write!(f, "associativity"),
+            HeaderField::Strategy => write!(f, "strategy (LRU, LFU or First)"),
+        }
+    }
+}
+
+/// Everything that can go wrong while reading a trace file.
+/// Line and column numbers start at one to match what text editors show.
+#[derive(Debug)]
+pub enum ParseError {
+    Io(io::Error),
+    /// The file ended before the header was complete.
+    MissingHeader { expected: HeaderField },
+    /// A header line does not contain a valid value for its field.
+    InvalidHeader {
+        line: usize,
+        column: usize,
+        text: String,
+        expected: HeaderField,
+    },
+    /// An address line is not a hexadecimal number. Only reported when reading strictly.
+    InvalidAddress {
+        line: usize,
+        column: usize,
+        text: String,
+    },
+    /// The header fields are valid on their own but do not describe a possible cache.
+    Geometry(GeometryError),
+    /// The first line is neither an address size nor a valid cache description.
+    InvalidSpec { line: usize, err: SpecError },
+    /// A line of a trace in a foreign format like Lackey is malformed. Only reported when reading strictly.
+    InvalidRecord {
+        line: usize,
+        column: usize,
+        text: String,
+        format: &'static str,
+    },
+    /// A binary trace is malformed at the given byte offset.
+    InvalidBinary { offset: u64, reason: String },
+    /// A loop nest kernel is malformed, or one of its accesses is out of bounds.
+    InvalidKernel { line: usize, message: String },
+    /// The trace does not describe the cache, so it has to be given by a configuration.
+    MissingCache { format: &'static str },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Io(err) => write!(f, "{}", err),
+            ParseError::MissingHeader { expected } => {
+                write!(f, "Missing {}: The file ends before the header is complete", expected)
+            }
+            ParseError::InvalidHeader { line, column, text, expected } => write!(
+                f,
+                "Line {}, column {}: Expected {}, found \"{}\"",
+                line, column, expected, text
+            ),
+            ParseError::InvalidAddress { line, column, text } => write!(
+                f,
+                "Line {}, column {}: Invalid hexadecimal address \"{}\"",
+                line, column, text
+            ),
+            ParseError::Geometry(err) => write!(f, "Invalid cache geometry: {}", err),
+            ParseError::InvalidSpec { line, err } => write!(
+                f,
+                "Line {}: {}. The header must either start with the address size in bits or be a \
+                 cache description like \"32KiB, 64B line, 8-way, LRU\"",
+                line, err
+            ),
+            ParseError::InvalidRecord { line, column, text, format } => write!(
+                f,
+                "Line {}, column {}: Invalid {} record \"{}\"",
+                line, column, format, text
+            ),
+            ParseError::InvalidBinary { offset, reason } => write!(f, "Byte {}: {}", offset, reason),
+            ParseError::InvalidKernel { line, message } => write!(f, "Line {}: {}", line, message),
+            ParseError::MissingCache { format } => write!(
+                f,
+                "The {} trace does not describe the cache, pass a configuration with --config or a cache with --cache",
+                format
+            ),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseError::Io(err) => Some(err),
+            ParseError::Geometry(err) => Some(err),
+            ParseError::InvalidSpec { err, .. } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<GeometryError> for ParseError {
+    fn from(err: GeometryError) -> Self {
+        ParseError::Geometry(err)
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(err: io::Error) -> Self {
+        ParseError::Io(err)
+    }
+}
+
+/// Finds the column of the first character in `text` that is not a digit in `radix`.
+/// Texts that are empty or only too large to fit point to the first column.
+fn invalid_column(text: &str, radix: u32) -> usize {
+    text.chars()
+        .position(|c| !c.is_digit(radix))
+        .map_or(1, |i| i + 1)
+}
+
+/// Reasons why a set of cache parameters does not describe a cache that can be simulated.
+#[derive(Debug, PartialEq, Eq)]
+pub enum GeometryError {
+    AddrSizeTooLarge { addr_size: u64 },
+    NoBlocks,
+    ZeroAssoc,
+    AssocDoesNotDivide { n_blocks: u64, assoc: u64 },
+    SetsNotPowerOfTwo { n_sets: u64 },
+    AddressTooShort { offset_bits: u64, idx_bits: u64, addr_size: u64 },
+}
+
+impl fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeometryError::AddrSizeTooLarge { addr_size } => write!(
+                f,
+                "Addresses of {} bits are not supported, the maximum is {}",
+                addr_size,
+                u64::BITS
+            ),
+            GeometryError::NoBlocks => write!(f, "The cache must contain at least one block"),
+            GeometryError::ZeroAssoc => write!(f, "The associativity must be at least 1"),
+            GeometryError::AssocDoesNotDivide { n_blocks, assoc } => write!(
+                f,
+                "The block count {} is not divisible by the associativity {}",
+                n_blocks, assoc
+            ),
+            GeometryError::SetsNotPowerOfTwo { n_sets } => write!(
+                f,
+                "The number of sets (block count / associativity = {}) must be a power of two",
+                n_sets
+            ),
+            GeometryError::AddressTooShort { offset_bits, idx_bits, addr_size } => write!(
+                f,
+                "{} offset bits and {} index bits do not fit into an address of {} bits",
+                offset_bits, idx_bits, addr_size
+            ),
+        }
+    }
+}
+
+impl Error for GeometryError {}
+
+#[derive(Clone, Debug)]
+pub struct CacheDesc {
+    addr_size: u64,
+    block_size: u64,
+    n_blocks: u64,
+    assoc: u64,
+    strat: Strategy,
+}
+
+impl CacheDesc {
+    /// Checks that the parameters describe a cache that can be simulated.
+    /// `block_size` is the number of offset bits, like in the trace header.
+    pub fn new(
+        addr_size: u64,
+        block_size: u64,
+        n_blocks: u64,
+        assoc: u64,
+        strat: Strategy,
+    ) -> Result<Self, GeometryError> {
+        if addr_size > u64::from(u64::BITS) {
+            return Err(GeometryError::AddrSizeTooLarge { addr_size });
+        }
+        if n_blocks == 0 {
+            return Err(GeometryError::NoBlocks);
+        }
+        if assoc == 0 {
+            return Err(GeometryError::ZeroAssoc);
+        }
+        if !n_blocks.is_multiple_of(assoc) {
+            return Err(GeometryError::AssocDoesNotDivide { n_blocks, assoc });
+        }
+
+        let cache = CacheDesc {
+            addr_size,
+            block_size,
+            n_blocks,
+            assoc,
+            strat,
+        };
+
+        // Only now is it safe to call n_sets and idx_bits.
+        if !cache.n_sets().is_power_of_two() {
+            return Err(GeometryError::SetsNotPowerOfTwo { n_sets: cache.n_sets() });
+        }
+        // The tag is found by shifting out the offset and index bits, which must leave at least
+        // one bit of a 64 bit address.
+        let low_bits = block_size.saturating_add(cache.idx_bits());
+        if low_bits > addr_size || low_bits >= u64::from(u64::BITS) {
+            return Err(GeometryError::AddressTooShort {
+                offset_bits: block_size,
+                idx_bits: cache.idx_bits(),
+                addr_size,
+            });
+        }
+
+        Ok(cache)
+    }
+
+    pub fn tag_bits(&self) -> u64 {
+        self.addr_size - self.offset_bits() - self.idx_bits()
+    }
+
+    pub fn addr_size(&self) -> u64 {
+        self.addr_size
+    }
+
+    pub fn n_blocks(&self) -> u64 {
+        self.n_blocks
+    }
+
+    pub fn assoc(&self) -> u64 {
+        self.assoc
+    }
+
+    pub fn strategy(&self) -> Strategy {
+        self.strat
+    }
+
+    pub fn n_sets(&self) -> u64 {
+        self.n_blocks / self.assoc
+    }
+
+    pub fn idx_bits(&self) -> u64 {
+        // log2(n_sets) =
+        (u64::BITS - self.n_sets().leading_zeros() - 1).into()
+    }
+
+    pub fn offset_bits(&self) -> u64 {
+        self.block_size
+    }
+}
+
+/// Values that replace parts of a cache description at run time, e.g. from the command line.
+/// The fields are named like the trace header, `block_size` is the number of offset bits.
+#[derive(Clone, Debug, Default)]
+pub struct CacheOverrides {
+    /// Replaces the whole description before the other fields are applied.
+    pub cache: Option<CacheDesc>,
+    pub addr_size: Option<u64>,
+    pub block_size: Option<u64>,
+    pub n_blocks: Option<u64>,
+    pub assoc: Option<u64>,
+    pub strategy: Option<Strategy>,
+}
+
+impl CacheOverrides {
+    /// Returns `cache` with the overridden parts replaced, checked like a new description.
+    pub fn apply(&self, cache: &CacheDesc) -> Result<CacheDesc, GeometryError> {
+        let cache = self.cache.as_ref().unwrap_or(cache);
+        CacheDesc::new(
+            self.addr_size.unwrap_or(cache.addr_size),
+            self.block_size.unwrap_or(cache.block_size),
+            self.n_blocks.unwrap_or(cache.n_blocks),
+            self.assoc.unwrap_or(cache.assoc),
+            self.strategy.unwrap_or(cache.strat),
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CacheEntry {
+    tag: u64,
+    last_used: u64,
+    count_used: u64,
+    entered: u64,
+}
+
+impl Display for CacheEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tag: {}", self.tag)
+    }
+}
+
+impl CacheEntry {
+    pub fn tag(&self) -> u64 {
+        self.tag
+    }
+    pub fn entered(&self) -> u64 {
+        self.entered
+    }
+    pub fn last_used(&self) -> u64 {
+        self.last_used
+    }
+    pub fn count_used(&self) -> u64 {
+        self.count_used
+    }
+}
+
+/// What happened during a single access to a cache level.
+#[derive(Clone, Debug)]
+pub struct AccessEvent {
+    /// Number of accesses the level saw before this one, the clock of `CacheEntry::entered`.
+    pub time: u64,
+    pub addr: u64,
+    pub tag: u64,
+    pub set: u64,
+    pub offset: u64,
+    pub hit: bool,
+    /// The way that hit or that was filled after the miss.
+    pub way: u64,
+    /// Tag of the entry that was replaced by the miss, if the way was not empty.
+    pub evicted: Option<u64>,
+}
+
+pub struct CacheStats {
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl CacheStats {
+  pub fn accesses(&self) -> u64 {
+    self.hits + self.misses
+  }
+  pub fn hits(&self) -> u64 {
+    self.hits
+  }
+  pub fn misses(&self) -> u64 {
+    self.misses
+  }
+  pub fn evictions(&self) -> u64 {
+    self.evictions
+  }
+}
+
+/// The outcome of simulating one level of a cache hierarchy.
+pub struct LevelResult {
+    pub name: String,
+    pub cache: CacheDesc,
+    /// Every entry each line held, oldest first, or only the final one without recorded history.
+    pub lines: Vec<Vec<CacheEntry>>,
+    /// Every access the level saw, empty without recorded history.
+    pub events: Vec<AccessEvent>,
+    pub stats: CacheStats,
+}
+
+/// Takes the next line from `lines` and parses it as the header field `expected`.
+fn read_header_field<'a, T: FromStr>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    expected: HeaderField,
+) -> Result<T, ParseError> {
+    let (line, text) = lines.next().ok_or(ParseError::MissingHeader { expected })?;
+
+    text.parse().map_err(|_| ParseError::InvalidHeader {
+        line,
+        column: if expected == HeaderField::Strategy { 1 } else { invalid_column(text, 10) },
+        text: text.to_string(),
+        expected,
+    })
+}
+
+/// Reads the five line header: address size, block size in bits, block count, associativity and strategy.
+fn read_legacy_header<'a>(lines: &mut impl Iterator<Item = (usize, &'a str)>) -> Result<CacheDesc, ParseError> {
+    let addr_size = read_header_field(lines, HeaderField::AddrSize)?;
+    let block_size = read_header_field(lines, HeaderField::BlockSize)?;
+    let n_blocks = read_header_field(lines, HeaderField::BlockCount)?;
+    let assoc = read_header_field(lines, HeaderField::Assoc)?;
+    let strat = read_header_field(lines, HeaderField::Strategy)?;
+
+    Ok(CacheDesc::new(addr_size, block_size, n_blocks, assoc, strat)?)
+}
+
+/// Reads either the five line header or a single line cache description
+/// like "32KiB, 64B line, 8-way, LRU" (see `CacheDesc::from_spec`).
+fn read_header<'a>(
+    lines: &mut Peekable<impl Iterator<Item = (usize, &'a str)>>,
+) -> Result<CacheDesc, ParseError> {
+    // The five line header starts with a plain number, a description never does.
+    match lines.peek() {
+        Some(&(line, text)) if text.parse::<u64>().is_err() => {
+            lines.next();
+            CacheDesc::from_spec(text).map_err(|err| ParseError::InvalidSpec { line, err })
+        }
+        _ => read_legacy_header(lines),
+    }
+}
+
+/// Reads the addresses of a native trace line by line.
+/// Lines that are not hexadecimal addresses are skipped, unless `strict` is set,
+/// in which case they are reported as errors. Empty lines, also ones with only whitespace, are
+/// always skipped. Whitespace around addresses is ignored.
+pub struct Addrs<R> {
+    /// Lines that were read while looking for the header, but are not part of it.
+    pending: vec::IntoIter<(usize, String)>,
+    lines: Enumerate<io::Lines<R>>,
+    strict: bool,
+}
+
+impl<R: BufRead> Iterator for Addrs<R> {
+    type Item = Result<u64, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (line, text) = match self.pending.next() {
+                Some(pending) => pending,
+                None => match self.lines.next()? {
+                    (i, Ok(text)) => (i + 1, text),
+                    (_, Err(err)) => return Some(Err(err.into())),
+                },
+            };
+
+            let addr = text.trim();
+            match u64::from_str_radix(addr, 16) {
+                Ok(addr) => return Some(Ok(addr)),
+                Err(_) if self.strict && !addr.is_empty() => {
+                    let indent = text.chars().take_while(|c| c.is_whitespace()).count();
+                    return Some(Err(ParseError::InvalidAddress {
+                        line,
+                        column: indent + invalid_column(addr, 16),
+                        text,
+                    }));
+                }
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+/// Starts reading a trace consisting of a header describing the cache followed by one hexadecimal
+/// address per line. Only the header is read right away, the addresses are read while iterating.
+///
+/// The cache may also be described elsewhere, e.g. in a config file, so traces without header can
+/// be read as well. Instead of the cache, the error in the header is returned for them.
+pub fn read<R: BufRead>(reader: R, strict: bool) -> Result<(Result<CacheDesc, ParseError>, Addrs<R>), ParseError> {
+    let mut lines = reader.lines().enumerate();
+
+    // The five line header is the longest one.
+    let mut head = Vec::with_capacity(5);
+    for (i, text) in lines.by_ref().take(5) {
+        head.push((i + 1, text?));
+    }
+
+    // Addresses never form a valid header: Neither a description nor a strategy in the fifth line
+    // are hexadecimal numbers.
+    let mut header_lines = head.iter().map(|(line, text)| (*line, text.as_str())).peekable();
+    let cache = read_header(&mut header_lines);
+    // A header that parses is skipped even if it describes no possible cache, its lines are no
+    // addresses either. The error then only explains why the trace has no cache.
+    let parsed = match &cache {
+        Ok(_) | Err(ParseError::Geometry(_)) => true,
+        Err(ParseError::InvalidSpec { err, .. }) => matches!(
+            err,
+            SpecError::Geometry(_) | SpecError::LineNotPowerOfTwo(_) | SpecError::SizeNotMultipleOfLine { .. }
+        ),
+        Err(_) => false,
+    };
+    let header_len = if parsed { head.len() - header_lines.count() } else { 0 };
+    head.drain(..header_len);
+
+    let addrs = Addrs {
+        pending: head.into_iter(),
+        lines,
+        strict,
+    };
+    Ok((cache, addrs))
+}
+
+/// Writes a trace that `read` accepts: a single line header if `cache` is given, followed by one
+/// hexadecimal address per line.
+pub fn write(writer: &mut impl Write, cache: Option<&CacheDesc>, addrs: impl IntoIterator<Item = u64>) -> io::Result<()> {
+    if let Some(cache) = cache {
+        writeln!(writer, "{}", cache.spec())?;
+    }
+    for addr in addrs {
+        writeln!(writer, "{:x}", addr)?;
+    }
+
+    writer.flush()
+}
+
+pub fn format_cache_line(line: &[CacheEntry], n: u64) -> String {
+    if line.is_empty() {
+        format!("{} | -", n)
+    } else {
+        format!(
+            "{} |{}",
+            n,
+            line.iter()
+                .map(|x| format!(" {:x} ({}) |", x.tag, x.entered))
+                .collect::<String>()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn geometry(addr_size: u64, block_size: u64, n_blocks: u64, assoc: u64) -> Result<CacheDesc, GeometryError> {
+        CacheDesc::new(addr_size, block_size, n_blocks, assoc, Strategy::LRU)
+    }
+
+    #[test]
+    fn valid_geometry() {
+        let cache = geometry(32, 6, 512, 8).unwrap();
+        assert_eq!((cache.n_sets(), cache.idx_bits(), cache.offset_bits(), cache.tag_bits()), (64, 6, 6, 20));
+
+        // The tag may take up all but a single bit of a 64 bit address.
+        let cache = geometry(64, 60, 16, 2).unwrap();
+        assert_eq!(cache.tag_bits(), 1);
+    }
+
+    #[test]
+    fn invalid_geometry() {
+        let error = |addr_size, block_size, n_blocks, assoc| geometry(addr_size, block_size, n_blocks, assoc).unwrap_err();
+
+        assert_eq!(error(65, 4, 16, 4), GeometryError::AddrSizeTooLarge { addr_size: 65 });
+        assert_eq!(error(32, 4, 0, 4), GeometryError::NoBlocks);
+        assert_eq!(error(32, 4, 16, 0), GeometryError::ZeroAssoc);
+        assert_eq!(error(32, 4, 16, 3), GeometryError::AssocDoesNotDivide { n_blocks: 16, assoc: 3 });
+        assert_eq!(error(32, 4, 12, 2), GeometryError::SetsNotPowerOfTwo { n_sets: 6 });
+        assert_eq!(
+            error(8, 6, 16, 2),
+            GeometryError::AddressTooShort { offset_bits: 6, idx_bits: 3, addr_size: 8 }
+        );
+        // Offset and index leave no bit for the tag of a 64 bit address.
+        assert_eq!(
+            error(64, 61, 16, 2),
+            GeometryError::AddressTooShort { offset_bits: 61, idx_bits: 3, addr_size: 64 }
+        );
+        assert_eq!(
+            error(64, u64::MAX, 1, 1),
+            GeometryError::AddressTooShort { offset_bits: u64::MAX, idx_bits: 0, addr_size: 64 }
+        );
+    }
+}
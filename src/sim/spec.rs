@@ -0,0 +1,315 @@
+// Human-friendly cache descriptions like "32KiB, 64B line, 8-way, LRU".
+
+use std::error::Error;
+use std::fmt;
+
+use super::{CacheDesc, GeometryError, Strategy};
+
+/// Address size in bits that is used when a description does not mention one.
+pub const DEFAULT_ADDR_SIZE: u64 = 32;
+
+/// Binary units from largest to smallest. Decimal spellings like "KB" are read as binary units too,
+/// since that is what they mean for caches.
+const UNITS: [(&str, u64); 4] = [("GiB", 1 << 30), ("MiB", 1 << 20), ("KiB", 1 << 10), ("B", 1)];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SpecError {
+    InvalidSize(String),
+    UnknownWord(String),
+    MissingSize,
+    MissingLineSize,
+    MissingAssoc,
+    LineNotPowerOfTwo(u64),
+    SizeNotMultipleOfLine { size: u64, line: u64 },
+    Geometry(GeometryError),
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpecError::InvalidSize(text) => write!(f, "\"{}\" is not a size like 32KiB or 64B", text),
+            SpecError::UnknownWord(text) => write!(f, "Unknown word \"{}\"", text),
+            SpecError::MissingSize => write!(f, "The total cache size is missing, e.g. 32KiB"),
+            SpecError::MissingLineSize => write!(f, "The line size is missing, e.g. 64B line"),
+            SpecError::MissingAssoc => write!(
+                f,
+                "The associativity is missing, e.g. 8-way, direct-mapped or fully-associative"
+            ),
+            SpecError::LineNotPowerOfTwo(line) => {
+                write!(f, "The line size of {} must be a power of two", format_size(*line))
+            }
+            SpecError::SizeNotMultipleOfLine { size, line } => write!(
+                f,
+                "The cache size of {} is not a multiple of the line size of {}",
+                format_size(*size),
+                format_size(*line)
+            ),
+            SpecError::Geometry(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for SpecError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SpecError::Geometry(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<GeometryError> for SpecError {
+    fn from(err: GeometryError) -> Self {
+        SpecError::Geometry(err)
+    }
+}
+
+/// Parses a size in bytes like "64B", "32KiB" or "1 MiB". A number without unit is a number of bytes.
+pub fn parse_size(text: &str) -> Result<u64, SpecError> {
+    let invalid = || SpecError::InvalidSize(text.to_string());
+
+    let text = text.trim();
+    let digits_end = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(digits_end);
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+
+    let factor = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        _ => return Err(invalid()),
+    };
+
+    number.checked_mul(factor).ok_or_else(invalid)
+}
+
+/// Formats a size in bytes with the largest binary unit that divides it, e.g. "32KiB".
+pub fn format_size(bytes: u64) -> String {
+    let (unit, factor) = UNITS
+        .iter()
+        .find(|(_, factor)| bytes != 0 && bytes.is_multiple_of(*factor))
+        .unwrap_or(&("B", 1));
+    format!("{}{}", bytes / factor, unit)
+}
+
+/// Parses a number followed by a suffix like "8-way", ignoring the case of the suffix.
+fn parse_suffixed(word: &str, suffix: &str) -> Option<u64> {
+    let lower = word.to_ascii_lowercase();
+    lower.strip_suffix(suffix)?.parse().ok()
+}
+
+/// Parses an associativity like "8-way", "direct-mapped" or "fully-associative".
+/// Fully associative caches have `None` ways, since that depends on the block count.
+pub fn parse_assoc(word: &str) -> Option<Option<u64>> {
+    if word.eq_ignore_ascii_case("direct-mapped") {
+        Some(Some(1))
+    } else if word.eq_ignore_ascii_case("fully-associative") {
+        Some(None)
+    } else {
+        parse_suffixed(word, "-way").map(Some)
+    }
+}
+
+fn is_line_word(word: &str) -> bool {
+    matches!(
+        word.to_ascii_lowercase().as_str(),
+        "line" | "lines" | "block" | "blocks"
+    )
+}
+
+impl CacheDesc {
+    /// Parses a description like "32KiB, 64B line, 8-way, LRU, 48-bit".
+    ///
+    /// Words may be separated by commas or spaces and given in any order. A size directly followed
+    /// by "line" or "block" is the line size, any other size is the total capacity. The strategy
+    /// defaults to LRU and the address size to 32 bits.
+    pub fn from_spec(spec: &str) -> Result<Self, SpecError> {
+        let mut size = None;
+        let mut line = None;
+        // None while not given, Some(None) for fully associative.
+        let mut assoc = None;
+        let mut addr_size = DEFAULT_ADDR_SIZE;
+        let mut strat = Strategy::LRU;
+
+        let mut words = spec
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|word| !word.is_empty())
+            .peekable();
+
+        while let Some(word) = words.next() {
+            if let Some(ways) = parse_assoc(word) {
+                assoc = Some(ways);
+            } else if let Some(bits) = parse_suffixed(word, "-bit") {
+                addr_size = bits;
+            } else if let Ok(parsed) = word.parse() {
+                strat = parsed;
+            } else if word.starts_with(|c: char| c.is_ascii_digit()) {
+                let bytes = parse_size(word)?;
+                if words.next_if(|next| is_line_word(next)).is_some() {
+                    line = Some(bytes);
+                } else {
+                    size = Some(bytes);
+                }
+            } else {
+                return Err(SpecError::UnknownWord(word.to_string()));
+            }
+        }
+
+        CacheDesc::from_sizes(
+            size.ok_or(SpecError::MissingSize)?,
+            line.ok_or(SpecError::MissingLineSize)?,
+            assoc.ok_or(SpecError::MissingAssoc)?,
+            strat,
+            addr_size,
+        )
+    }
+
+    /// Derives the cache geometry from its capacity and line size in bytes.
+    /// An associativity of `None` means fully associative.
+    pub fn from_sizes(
+        size: u64,
+        line: u64,
+        assoc: Option<u64>,
+        strat: Strategy,
+        addr_size: u64,
+    ) -> Result<Self, SpecError> {
+        if !line.is_power_of_two() {
+            return Err(SpecError::LineNotPowerOfTwo(line));
+        }
+        if !size.is_multiple_of(line) {
+            return Err(SpecError::SizeNotMultipleOfLine { size, line });
+        }
+
+        let n_blocks = size / line;
+        let assoc = assoc.unwrap_or(n_blocks);
+
+        Ok(CacheDesc::new(addr_size, line.trailing_zeros().into(), n_blocks, assoc, strat)?)
+    }
+
+    /// Formats the cache in the notation `from_spec` accepts.
+    pub fn spec(&self) -> String {
+        let line = 1u64 << self.block_size;
+        let assoc = match self.assoc {
+            1 => "direct-mapped".to_string(),
+            assoc if assoc == self.n_blocks => "fully-associative".to_string(),
+            assoc => format!("{}-way", assoc),
+        };
+
+        format!(
+            "{}, {} line, {}, {}, {}-bit",
+            format_size(self.n_blocks.saturating_mul(line)),
+            format_size(line),
+            assoc,
+            self.strat,
+            self.addr_size
+        )
+    }
+}
+
+impl fmt::Display for CacheDesc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (address size: {}, block size: {} bits, block count: {}, associativity: {}, sets: {})",
+            self.spec(),
+            self.addr_size,
+            self.block_size,
+            self.n_blocks,
+            self.assoc,
+            self.n_sets()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The fields of `cache`: address size, offset bits, block count, associativity and strategy.
+    fn fields(cache: &CacheDesc) -> (u64, u64, u64, u64, Strategy) {
+        (cache.addr_size, cache.block_size, cache.n_blocks, cache.assoc, cache.strat)
+    }
+
+    fn parse(spec: &str) -> (u64, u64, u64, u64, Strategy) {
+        fields(&CacheDesc::from_spec(spec).unwrap())
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("64B"), Ok(64));
+        assert_eq!(parse_size("100"), Ok(100));
+        assert_eq!(parse_size("32KiB"), Ok(32 << 10));
+        // Decimal spellings mean binary units as well.
+        assert_eq!(parse_size("32KB"), Ok(32 << 10));
+        assert_eq!(parse_size("32kb"), Ok(32 << 10));
+        assert_eq!(parse_size(" 1 MiB "), Ok(1 << 20));
+        assert_eq!(parse_size("2G"), Ok(2 << 30));
+
+        for invalid in ["KiB", "", "32XB", "-1B", "1.5KiB", "17179869184GiB"] {
+            assert_eq!(parse_size(invalid), Err(SpecError::InvalidSize(invalid.to_string())));
+        }
+
+        assert_eq!(format_size(32 << 10), "32KiB");
+        assert_eq!(format_size(1536), "1536B");
+        assert_eq!(format_size(0), "0B");
+    }
+
+    #[test]
+    fn associativities() {
+        assert_eq!(parse("32KiB, 64B line, 8-way, LRU, 48-bit"), (48, 6, 512, 8, Strategy::LRU));
+        assert_eq!(parse("1KB 16B block direct-mapped First"), (32, 4, 64, 1, Strategy::First));
+        assert_eq!(parse("LFU, fully-associative, 16B line, 256B"), (32, 4, 16, 16, Strategy::LFU));
+        assert_eq!(parse("256B, 16B Lines, 4-Way"), (32, 4, 16, 4, Strategy::LRU));
+    }
+
+    #[test]
+    fn spec_round_trip() {
+        let caches = [
+            CacheDesc::new(32, 6, 512, 8, Strategy::LRU),
+            CacheDesc::new(48, 4, 64, 1, Strategy::First),
+            CacheDesc::new(12, 4, 16, 16, Strategy::LFU),
+            CacheDesc::new(64, 12, 1 << 20, 16, Strategy::LRU),
+            CacheDesc::new(8, 0, 1, 1, Strategy::LRU),
+        ];
+        for cache in caches {
+            let cache = cache.unwrap();
+            let parsed = CacheDesc::from_spec(&cache.spec()).unwrap();
+            assert_eq!(fields(&parsed), fields(&cache), "{}", cache.spec());
+        }
+
+        assert_eq!(
+            CacheDesc::new(32, 6, 512, 8, Strategy::LRU).unwrap().spec(),
+            "32KiB, 64B line, 8-way, LRU, 32-bit"
+        );
+        assert_eq!(
+            CacheDesc::new(12, 4, 16, 16, Strategy::LFU).unwrap().spec(),
+            "256B, 16B line, fully-associative, LFU, 12-bit"
+        );
+    }
+
+    #[test]
+    fn invalid_specs() {
+        let error = |spec: &str| CacheDesc::from_spec(spec).unwrap_err();
+
+        assert_eq!(error(""), SpecError::MissingSize);
+        assert_eq!(error("32KiB, 8-way"), SpecError::MissingLineSize);
+        assert_eq!(error("32KiB, 64B line"), SpecError::MissingAssoc);
+        assert_eq!(error("32KiB, 64B line, 8-way, fast"), SpecError::UnknownWord("fast".to_string()));
+        assert_eq!(error("32XB, 64B line, 8-way"), SpecError::InvalidSize("32XB".to_string()));
+        assert_eq!(error("48KiB, 48B line, 2-way"), SpecError::LineNotPowerOfTwo(48));
+        assert_eq!(error("100B, 64B line, 1-way"), SpecError::SizeNotMultipleOfLine { size: 100, line: 64 });
+        assert_eq!(
+            error("32KiB, 64B line, 3-way"),
+            SpecError::Geometry(GeometryError::AssocDoesNotDivide { n_blocks: 512, assoc: 3 })
+        );
+        assert_eq!(
+            error("32KiB, 64B line, 0-way"),
+            SpecError::Geometry(GeometryError::ZeroAssoc)
+        );
+        assert_eq!(
+            error("32KiB, 64B line, 8-way, 8-bit"),
+            SpecError::Geometry(GeometryError::AddressTooShort { offset_bits: 6, idx_bits: 6, addr_size: 8 })
+        );
+    }
+}
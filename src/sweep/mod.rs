@@ -0,0 +1,223 @@
+// Simulations of a trace with several caches at once, e.g. to see how the hit rate changes with
+// the associativity. The caches are all combinations of the values given for the fields of a base
+// cache, combinations that do not describe a cache are skipped. The trace is read once and every
+// access goes to all caches without recording history, so sweeps also work for traces from stdin.
+//
+// Version 1 of the JSON output:
+//
+//     {
+//       "version": 1,
+//       "caches": [
+//         {
+//           "spec": "64B, 16B line, fully-associative, LRU, 32-bit", "address_bits": 32, "offset_bits": 4,
+//           "blocks": 4, "associativity": 4, "policy": "LRU",
+//           "accesses": 5, "hits": 1, "misses": 4, "evictions": 0, "hit_rate": 0.2
+//         }
+//       ]
+//     }
+//
+// The CSV output has a row for every cache with the same names as columns:
+//
+//     spec,address_bits,offset_bits,blocks,associativity,policy,accesses,hits,misses,evictions,hit_rate
+//
+// Caches are in the order of the combinations, varying the strategy fastest, then the
+// associativity, the block count, the block size and the address size.
+
+use std::io::{self, Write};
+use std::slice;
+
+use crate::export::OutputFormat;
+use crate::sim::{CacheDesc, CacheOverrides, CacheStats, Hierarchy, ParseError, Strategy};
+use crate::trace::Accesses;
+
+/// The newest version of the JSON and CSV output.
+pub const VERSION: u32 = 1;
+
+/// The values to try for every field. Empty lists keep the value of the base cache.
+#[derive(Clone, Debug, Default)]
+pub struct Sweep {
+    pub addr_sizes: Vec<u64>,
+    pub block_sizes: Vec<u64>,
+    pub n_blocks: Vec<u64>,
+    pub assocs: Vec<u64>,
+    pub strategies: Vec<Strategy>,
+}
+
+/// The values of `list` as overrides, or no override if it is empty.
+fn values<T: Copy>(list: &[T]) -> Vec<Option<T>> {
+    if list.is_empty() {
+        vec![None]
+    } else {
+        list.iter().copied().map(Some).collect()
+    }
+}
+
+impl Sweep {
+    /// Every combination applied to `base`. Combinations that do not describe a cache are
+    /// returned as messages instead.
+    pub fn caches(&self, base: &CacheDesc) -> (Vec<CacheDesc>, Vec<String>) {
+        let mut caches = Vec::new();
+        let mut skipped = Vec::new();
+
+        for addr_size in values(&self.addr_sizes) {
+            for block_size in values(&self.block_sizes) {
+                for n_blocks in values(&self.n_blocks) {
+                    for assoc in values(&self.assocs) {
+                        for strategy in values(&self.strategies) {
+                            let overrides = CacheOverrides {
+                                cache: None,
+                                addr_size,
+                                block_size,
+                                n_blocks,
+                                assoc,
+                                strategy,
+                            };
+                            match overrides.apply(base) {
+                                Ok(cache) => caches.push(cache),
+                                Err(err) => skipped.push(format!("{}: {}", describe(&overrides), err)),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        (caches, skipped)
+    }
+}
+
+/// The overridden fields, e.g. "block count 16, associativity 32".
+fn describe(overrides: &CacheOverrides) -> String {
+    let fields = [
+        ("address size", overrides.addr_size),
+        ("block size", overrides.block_size),
+        ("block count", overrides.n_blocks),
+        ("associativity", overrides.assoc),
+    ];
+    let mut parts: Vec<String> = fields
+        .iter()
+        .filter_map(|(name, value)| value.map(|value| format!("{} {}", name, value)))
+        .collect();
+    if let Some(strategy) = overrides.strategy {
+        parts.push(strategy.to_string());
+    }
+    parts.join(", ")
+}
+
+/// Simulates the accesses with every cache on its own and returns their statistics.
+pub fn run(accesses: Accesses, caches: &[CacheDesc]) -> Result<Vec<CacheStats>, ParseError> {
+    let mut hierarchies: Vec<Hierarchy> = caches
+        .iter()
+        .map(|cache| Hierarchy::new(slice::from_ref(cache), false))
+        .collect();
+
+    for access in accesses {
+        let access = access?;
+        for (cache, hierarchy) in caches.iter().zip(&mut hierarchies) {
+            for addr in access.line_addrs(cache.offset_bits()) {
+                hierarchy.access(addr);
+            }
+        }
+    }
+
+    Ok(hierarchies
+        .into_iter()
+        .map(|hierarchy| {
+            let (_, _, stats) = hierarchy.finish().pop().expect("The hierarchy has a single level.");
+            stats
+        })
+        .collect())
+}
+
+fn hit_rate(stats: &CacheStats) -> f64 {
+    if stats.accesses() == 0 {
+        0.0
+    } else {
+        stats.hits() as f64 / stats.accesses() as f64
+    }
+}
+
+#[derive(serde::Serialize)]
+struct Document {
+    version: u32,
+    caches: Vec<Row>,
+}
+
+#[derive(serde::Serialize)]
+struct Row {
+    spec: String,
+    address_bits: u64,
+    offset_bits: u64,
+    blocks: u64,
+    associativity: u64,
+    policy: String,
+    accesses: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+    hit_rate: f64,
+}
+
+/// Writes a row for every cache and its statistics as text, JSON or CSV.
+pub fn write(writer: &mut impl Write, format: OutputFormat, caches: &[CacheDesc], stats: &[CacheStats]) -> io::Result<()> {
+    let rows: Vec<Row> = caches
+        .iter()
+        .zip(stats)
+        .map(|(cache, stats)| Row {
+            spec: cache.spec(),
+            address_bits: cache.addr_size(),
+            offset_bits: cache.offset_bits(),
+            blocks: cache.n_blocks(),
+            associativity: cache.assoc(),
+            policy: cache.strategy().to_string(),
+            accesses: stats.accesses(),
+            hits: stats.hits(),
+            misses: stats.misses(),
+            evictions: stats.evictions(),
+            hit_rate: hit_rate(stats),
+        })
+        .collect();
+
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer(&mut *writer, &Document { version: VERSION, caches: rows })?;
+            writeln!(writer)?;
+        }
+        OutputFormat::Csv => {
+            writeln!(writer, "spec,address_bits,offset_bits,blocks,associativity,policy,accesses,hits,misses,evictions,hit_rate")?;
+            for row in rows {
+                writeln!(
+                    writer,
+                    "\"{}\",{},{},{},{},{},{},{},{},{},{}",
+                    row.spec, row.address_bits, row.offset_bits, row.blocks, row.associativity, row.policy,
+                    row.accesses, row.hits, row.misses, row.evictions, row.hit_rate,
+                )?;
+            }
+        }
+        _ => {
+            let spec = rows.iter().map(|row| row.spec.len()).fold("Cache".len(), usize::max);
+            let number = |value: fn(&Row) -> u64, title: &str| {
+                rows.iter().map(|row| value(row).to_string().len()).fold(title.len(), usize::max)
+            };
+            let accesses = number(|row| row.accesses, "Accesses");
+            let hits = number(|row| row.hits, "Hits");
+            let misses = number(|row| row.misses, "Misses");
+            let evictions = number(|row| row.evictions, "Evictions");
+
+            writeln!(
+                writer,
+                "{:<spec$}  {:>accesses$}  {:>hits$}  {:>misses$}  {:>evictions$}  Hit rate",
+                "Cache", "Accesses", "Hits", "Misses", "Evictions",
+            )?;
+            for row in rows {
+                writeln!(
+                    writer,
+                    "{:<spec$}  {:>accesses$}  {:>hits$}  {:>misses$}  {:>evictions$}  {:>7.1}%",
+                    row.spec, row.accesses, row.hits, row.misses, row.evictions, row.hit_rate * 100.0,
+                )?;
+            }
+        }
+    }
+
+    writer.flush()
+}
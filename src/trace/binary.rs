@@ -0,0 +1,324 @@
+// Compact binary traces for very large traces.
+//
+// A binary trace starts with the magic bytes "CCTR" and a version byte, followed by the cache
+// description in the notation of `CacheDesc::from_spec` as a varint length and UTF-8 bytes.
+// A length of zero means the trace does not describe its cache.
+//
+// Every access is a varint containing, from the least significant bit on:
+// - 2 bits access kind: 0 = load, 1 = store, 2 = modify, 3 = instruction
+// - 1 bit telling whether the size changed, in which case the new size follows as a varint
+// - the difference to the previous address, zigzag encoded so small negative steps stay short.
+// The first address is relative to 0 and the first size is 1.
+//
+// Varints are unsigned LEB128: 7 bits per byte, least significant first, high bit set on all
+// but the last byte.
+
+use std::io::{self, BufRead, Read, Write};
+
+use crate::sim::{CacheDesc, ParseError};
+
+use super::{Access, AccessKind};
+
+pub const MAGIC: &[u8; 4] = b"CCTR";
+pub const VERSION: u8 = 1;
+
+/// A record needs at most 64 address bits and 3 bits of flags.
+const MAX_VARINT_BYTES: u32 = 10;
+
+fn zigzag(delta: i64) -> u64 {
+    ((delta << 1) ^ (delta >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn write_varint(writer: &mut impl Write, mut value: u128) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn kind_code(kind: AccessKind) -> u128 {
+    match kind {
+        AccessKind::Load => 0,
+        AccessKind::Store => 1,
+        AccessKind::Modify => 2,
+        AccessKind::Instruction => 3,
+    }
+}
+
+/// Writes a binary trace access by access.
+pub struct Writer<W: Write> {
+    writer: W,
+    prev_addr: u64,
+    prev_size: u64,
+}
+
+impl<W: Write> Writer<W> {
+    /// Writes the header. `cache` is stored so the trace can be simulated without configuration.
+    pub fn new(mut writer: W, cache: Option<&CacheDesc>) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+
+        let spec = cache.map(CacheDesc::spec).unwrap_or_default();
+        write_varint(&mut writer, spec.len() as u128)?;
+        writer.write_all(spec.as_bytes())?;
+
+        Ok(Writer {
+            writer,
+            prev_addr: 0,
+            prev_size: 1,
+        })
+    }
+
+    pub fn write(&mut self, access: &Access) -> io::Result<()> {
+        let delta = zigzag(access.addr.wrapping_sub(self.prev_addr) as i64);
+        let size_changed = access.size != self.prev_size;
+
+        write_varint(
+            &mut self.writer,
+            (u128::from(delta) << 3) | (u128::from(size_changed) << 2) | kind_code(access.kind),
+        )?;
+        if size_changed {
+            write_varint(&mut self.writer, access.size.into())?;
+        }
+
+        self.prev_addr = access.addr;
+        self.prev_size = access.size;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reads a binary trace while keeping track of the position for error messages.
+struct Decoder<R> {
+    bytes: io::Bytes<R>,
+    offset: u64,
+}
+
+impl<R: Read> Decoder<R> {
+    fn error(&self, reason: impl Into<String>) -> ParseError {
+        ParseError::InvalidBinary {
+            offset: self.offset,
+            reason: reason.into(),
+        }
+    }
+
+    /// Returns `None` at the end of the trace.
+    fn byte(&mut self) -> Result<Option<u8>, ParseError> {
+        let byte = self.bytes.next().transpose()?;
+        if byte.is_some() {
+            self.offset += 1;
+        }
+        Ok(byte)
+    }
+
+    /// Returns `None` if the trace ends before the varint starts.
+    fn varint(&mut self) -> Result<Option<u128>, ParseError> {
+        let mut value = 0u128;
+        for i in 0..MAX_VARINT_BYTES {
+            let Some(byte) = self.byte()? else {
+                return if i == 0 {
+                    Ok(None)
+                } else {
+                    Err(self.error("The trace ends in the middle of a number"))
+                };
+            };
+
+            value |= u128::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(Some(value));
+            }
+        }
+
+        Err(self.error("Number too long"))
+    }
+
+    fn required_varint(&mut self) -> Result<u128, ParseError> {
+        self.varint()?
+            .ok_or_else(|| self.error("The trace ends in the middle of a record"))
+    }
+}
+
+/// Reads the accesses of a binary trace one by one.
+pub struct Reader<R> {
+    decoder: Decoder<R>,
+    addr: u64,
+    size: u64,
+}
+
+impl<R: Read> Reader<R> {
+    /// Returns `None` at the end of the trace.
+    fn record(&mut self) -> Result<Option<Access>, ParseError> {
+        let decoder = &mut self.decoder;
+        let Some(record) = decoder.varint()? else {
+            return Ok(None);
+        };
+
+        let delta = u64::try_from(record >> 3).map_err(|_| decoder.error("Address difference too large"))?;
+        if record & 0b100 != 0 {
+            self.size = u64::try_from(decoder.required_varint()?)
+                .map_err(|_| decoder.error("Access size too large"))?;
+        }
+
+        let kind = match record & 0b11 {
+            0 => AccessKind::Load,
+            1 => AccessKind::Store,
+            2 => AccessKind::Modify,
+            _ => AccessKind::Instruction,
+        };
+
+        self.addr = self.addr.wrapping_add(unzigzag(delta) as u64);
+        Ok(Some(Access {
+            addr: self.addr,
+            size: self.size,
+            kind,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = Result<Access, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.record().transpose()
+    }
+}
+
+/// Reads the header of a binary trace. Returns the cache it describes, if any, and a reader for
+/// its accesses.
+pub fn read<R: BufRead>(reader: R) -> Result<(Option<CacheDesc>, Reader<R>), ParseError> {
+    let mut decoder = Decoder {
+        bytes: reader.bytes(),
+        offset: 0,
+    };
+
+    for expected in MAGIC {
+        if decoder.byte()? != Some(*expected) {
+            return Err(decoder.error("Not a cachecache binary trace"));
+        }
+    }
+    match decoder.byte()? {
+        Some(VERSION) => {}
+        Some(version) => {
+            return Err(decoder.error(format!("Unsupported binary trace version {}", version)))
+        }
+        None => return Err(decoder.error("The trace ends in its header")),
+    }
+
+    let spec_len = decoder.required_varint()?;
+    let mut spec = Vec::new();
+    for _ in 0..spec_len {
+        spec.push(decoder.byte()?.ok_or_else(|| decoder.error("The trace ends in its header"))?);
+    }
+    let cache = if spec.is_empty() {
+        None
+    } else {
+        let spec = String::from_utf8(spec).map_err(|_| decoder.error("Invalid cache description"))?;
+        let cache = CacheDesc::from_spec(&spec)
+            .map_err(|err| decoder.error(format!("Invalid cache description: {}", err)))?;
+        Some(cache)
+    };
+
+    let reader = Reader {
+        decoder,
+        addr: 0,
+        size: 1,
+    };
+    Ok((cache, reader))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn access(addr: u64, size: u64, kind: AccessKind) -> Access {
+        Access { addr, size, kind }
+    }
+
+    fn encode(cache: Option<&CacheDesc>, accesses: &[Access]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut writer = Writer::new(&mut bytes, cache).unwrap();
+        for access in accesses {
+            writer.write(access).unwrap();
+        }
+        writer.finish().unwrap();
+        bytes
+    }
+
+    #[test]
+    fn zigzag_round_trip() {
+        for delta in [0, 1, -1, 63, -64, i64::MAX, i64::MIN] {
+            assert_eq!(unzigzag(zigzag(delta)), delta);
+        }
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+    }
+
+    #[test]
+    fn accesses_round_trip() {
+        let accesses = [
+            access(0x1000, 1, AccessKind::Load),
+            access(0x1040, 1, AccessKind::Store),
+            // Negative deltas, also across the whole address space.
+            access(0x0ff8, 1, AccessKind::Modify),
+            access(u64::MAX, 1, AccessKind::Load),
+            access(0, 1, AccessKind::Instruction),
+            // Size changes and back.
+            access(0x2000, 8, AccessKind::Load),
+            access(0x2008, 8, AccessKind::Store),
+            access(0x2010, 1, AccessKind::Load),
+            access(u64::MAX - 3, 4, AccessKind::Modify),
+        ];
+        let cache = CacheDesc::from_spec("32KiB, 64B line, 8-way, LRU").unwrap();
+
+        let bytes = encode(Some(&cache), &accesses);
+        let (read_cache, reader) = read(bytes.as_slice()).unwrap();
+        assert_eq!(read_cache.map(|cache| cache.spec()), Some(cache.spec()));
+        assert_eq!(reader.collect::<Result<Vec<_>, _>>().unwrap(), accesses);
+    }
+
+    #[test]
+    fn trace_without_cache() {
+        let accesses = [access(0x10, 1, AccessKind::Load)];
+        let bytes = encode(None, &accesses);
+        assert_eq!(&bytes[..5], b"CCTR\x01");
+        assert_eq!(bytes[5], 0);
+
+        let (cache, reader) = read(bytes.as_slice()).unwrap();
+        assert!(cache.is_none());
+        assert_eq!(reader.collect::<Result<Vec<_>, _>>().unwrap(), accesses);
+    }
+
+    #[test]
+    fn invalid_header() {
+        let error = |bytes: &[u8]| match read(bytes) {
+            Err(ParseError::InvalidBinary { offset, reason }) => (offset, reason),
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(_) => panic!("{:?} was accepted", bytes),
+        };
+
+        assert_eq!(error(b"CCTX\x01\x00"), (4, "Not a cachecache binary trace".to_string()));
+        assert_eq!(error(b"CCTR\x02\x00"), (5, "Unsupported binary trace version 2".to_string()));
+        assert_eq!(error(b"CCTR"), (4, "The trace ends in its header".to_string()));
+        assert_eq!(error(b"CCTR\x01\x03ab"), (8, "The trace ends in its header".to_string()));
+        assert!(error(b"CCTR\x01\x03abc").1.starts_with("Invalid cache description"));
+    }
+
+    #[test]
+    fn truncated_record() {
+        let mut bytes = encode(None, &[access(0x1_0000_0000, 1, AccessKind::Load)]);
+        bytes.pop();
+        let (_, mut reader) = read(bytes.as_slice()).unwrap();
+        assert!(matches!(reader.next(), Some(Err(ParseError::InvalidBinary { .. }))));
+    }
+}
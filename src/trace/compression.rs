@@ -0,0 +1,128 @@
+// Traces compressed with gzip, zstd or xz are decompressed while they are read. The compression
+// is detected by its magic bytes, so it works for every trace format and any file name.
+
+use std::io::{self, BufRead, BufReader, Read};
+
+use flate2::read::MultiGzDecoder;
+use xz2::read::XzDecoder;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+/// Length of the longest magic.
+const MAGIC_LEN: u64 = 6;
+
+/// Returns a reader for the decompressed content of `reader`, or for the content itself if it is
+/// not compressed. Streams of several concatenated members, like those of pigz, are read entirely.
+pub fn decompress(mut reader: impl Read + 'static) -> io::Result<Box<dyn BufRead>> {
+    // Read the magic up front instead of peeking at a buffer, since pipes may deliver fewer bytes.
+    let mut magic = Vec::new();
+    reader.by_ref().take(MAGIC_LEN).read_to_end(&mut magic)?;
+
+    let is_gzip = magic.starts_with(GZIP_MAGIC);
+    let is_zstd = magic.starts_with(ZSTD_MAGIC);
+    let is_xz = magic.starts_with(XZ_MAGIC);
+    let reader = io::Cursor::new(magic).chain(reader);
+
+    Ok(if is_gzip {
+        Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+    } else if is_zstd {
+        Box::new(BufReader::new(zstd::Decoder::new(reader)?))
+    } else if is_xz {
+        Box::new(BufReader::new(XzDecoder::new_multi_decoder(reader)))
+    } else {
+        Box::new(BufReader::new(reader))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use xz2::write::XzEncoder;
+
+    use crate::trace::{self, Access};
+
+    use super::*;
+
+    /// A file in the temporary directory that is removed when it is dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, content: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!("cachecache-{}-{}", std::process::id(), name));
+            fs::write(&path, content).unwrap();
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    /// The cache and accesses of the trace at `path`, guessing its format.
+    fn read(path: &Path) -> (Option<String>, Vec<Access>) {
+        let trace = trace::open(path, None, true).unwrap();
+        let accesses = trace.accesses.collect::<Result<_, _>>().unwrap();
+        (trace.cache.ok().map(|cache| cache.spec()), accesses)
+    }
+
+    fn gzip(content: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn xz(content: &[u8]) -> Vec<u8> {
+        let mut encoder = XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(content).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn round_trips() {
+        for (name, content) in [
+            ("evict", &include_bytes!("../../test/evict")[..]),
+            ("sample.lackey", &include_bytes!("../../test/sample.lackey")[..]),
+            ("sample.din", &include_bytes!("../../test/sample.din")[..]),
+        ] {
+            let expected = read(&TempFile::new(name, content).0);
+            assert!(!expected.1.is_empty());
+
+            for (extension, compressed) in [
+                ("gz", gzip(content)),
+                ("zst", zstd::encode_all(content, 0).unwrap()),
+                ("xz", xz(content)),
+            ] {
+                let file = TempFile::new(&format!("{}.{}", name, extension), &compressed);
+                assert_eq!(read(&file.0), expected, "{}.{}", name, extension);
+            }
+        }
+    }
+
+    #[test]
+    fn concatenated_gzip_members() {
+        let content = include_bytes!("../../test/evict");
+        let (first, second) = content.split_at(content.len() / 2);
+        let mut compressed = gzip(first);
+        compressed.extend(gzip(second));
+
+        let expected = read(&TempFile::new("members", content).0);
+        assert_eq!(read(&TempFile::new("members.gz", &compressed).0), expected);
+    }
+
+    #[test]
+    fn uncompressed_content() {
+        // Shorter than the longest magic.
+        let mut content = String::new();
+        decompress(io::Cursor::new(b"ab".to_vec())).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "ab");
+    }
+}
@@ -0,0 +1,85 @@
+// The din format of the Dinero IV cache simulator. Every line contains a label and a hexadecimal
+// address, e.g. "0 7ff000398". An optional third field is read as the access size in bytes.
+//
+// Labels: 0 = read, 1 = write, 2 = instruction fetch, 3 = escape, 4 = cache flush.
+// Escapes and flushes are not accesses and are skipped.
+
+use std::io::BufRead;
+
+use super::{column, Access, AccessKind, Format, Records};
+
+/// Parses a single record. Returns the column of the malformed field if it is malformed and
+/// `Ok(None)` if it is valid, but not an access.
+pub fn parse_record(text: &str) -> Result<Option<Access>, usize> {
+    let mut fields = text.split_whitespace();
+    let end = &text[text.len()..];
+
+    let label = fields.next().unwrap_or(end);
+    let kind = match label {
+        "0" => Some(AccessKind::Load),
+        "1" => Some(AccessKind::Store),
+        "2" => Some(AccessKind::Instruction),
+        "3" | "4" => None,
+        _ => return Err(column(text, label)),
+    };
+
+    let addr = fields.next().unwrap_or(end);
+    let addr = u64::from_str_radix(addr.trim_start_matches("0x"), 16).map_err(|_| column(text, addr))?;
+    let size = match fields.next() {
+        Some(size) => size.parse().map_err(|_| column(text, size))?,
+        None => 1,
+    };
+
+    Ok(kind.map(|kind| Access { addr, size, kind }))
+}
+
+pub fn records<R: BufRead>(reader: R, strict: bool) -> Records<R> {
+    Records {
+        lines: reader.lines().enumerate(),
+        strict,
+        format: Format::Dinero,
+        skip: |_| false,
+        parse_record,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sim::ParseError;
+
+    use super::*;
+
+    /// The line and column of the first malformed record of `trace`.
+    fn error(trace: &str) -> (usize, usize) {
+        match records(trace.as_bytes(), true).find_map(Result::err) {
+            Some(ParseError::InvalidRecord { line, column, format: "Dinero", .. }) => (line, column),
+            err => panic!("unexpected result {:?} for {:?}", err, trace),
+        }
+    }
+
+    #[test]
+    fn access_kinds() {
+        let trace = "0 7ff000398\n1 0x1000 8\n\n2 abe\n3 0\n4 0\n";
+        let accesses: Vec<_> = records(trace.as_bytes(), true).collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            accesses,
+            [
+                Access { addr: 0x7ff000398, size: 1, kind: AccessKind::Load },
+                Access { addr: 0x1000, size: 8, kind: AccessKind::Store },
+                Access { addr: 0xabe, size: 1, kind: AccessKind::Instruction },
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_records() {
+        assert_eq!(error("0 1000\n5 1000"), (2, 1));
+        assert_eq!(error("0  10zz"), (1, 4));
+        assert_eq!(error("0 1000 eight"), (1, 8));
+        // A missing address is reported after the end of the line.
+        assert_eq!(error("1"), (1, 2));
+
+        // Without `strict`, malformed records are skipped.
+        assert_eq!(records("5 1000\n0 10".as_bytes(), false).count(), 1);
+    }
+}
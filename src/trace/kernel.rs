@@ -0,0 +1,707 @@
+// Loop nests that expand into traces, to compare loop orders and tiling without writing and
+// tracing a program, e.g.
+//
+//     cache 4KiB, 64B line, 4-way
+//     let N = 64
+//     array A[N][N] size 8 at 0x10000
+//     array B[N][N] size 8
+//     for i in 0..N {
+//         for j in 0..N { read A[i][j]; write B[j][i] }
+//     }
+//
+// Arrays are stored row by row. `size` is the element size in bytes and defaults to 8. Without
+// `at`, an array directly follows the previous one, the first one starts at 0. Loops count from
+// the first bound up to, but excluding, the second one, optionally with `step`. Bounds and indices
+// are integer expressions of constants and loop variables using + - * / %, parentheses and
+// min(a, b) and max(a, b). Accesses are `read`, `write` or `modify`.
+//
+// Statements may be separated by semicolons or line breaks. `#` starts a comment. A `cache` line
+// describes the cache like the header of native traces.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::sim::{CacheDesc, ParseError};
+
+use super::{Access, AccessKind};
+
+const KEYWORDS: [&str; 7] = ["cache", "let", "array", "for", "read", "write", "modify"];
+
+/// Whether a line, the first one that is not empty, starts a kernel.
+pub fn is_kernel(text: &str) -> bool {
+    let text = text.trim_start();
+    text.starts_with('#')
+        || KEYWORDS.contains(&text.split(|c: char| !c.is_alphanumeric()).next().unwrap_or(""))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(i64),
+    Symbol(&'static str),
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "\"{}\"", name),
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Symbol(symbol) => write!(f, "\"{}\"", symbol),
+            Token::End => write!(f, "the end of the file"),
+        }
+    }
+}
+
+/// Longer symbols first, so ".." is not read as two dots.
+const SYMBOLS: [&str; 15] = ["..", "{", "}", "[", "]", "(", ")", ",", ";", "=", "+", "-", "*", "/", "%"];
+
+fn error(line: usize, message: impl Into<String>) -> ParseError {
+    ParseError::InvalidKernel {
+        line,
+        message: message.into(),
+    }
+}
+
+/// Splits a line into tokens, all tagged with the line number.
+fn tokenize(line: usize, text: &str, tokens: &mut Vec<(usize, Token)>) -> Result<(), ParseError> {
+    let text = text.split('#').next().unwrap_or("");
+    let mut rest = text.trim_start();
+
+    while !rest.is_empty() {
+        let token_len = if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) {
+            tokens.push((line, Token::Symbol(symbol)));
+            symbol.len()
+        } else if rest.starts_with(|c: char| c.is_ascii_digit()) {
+            let len = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+            let word = &rest[..len];
+            let number = match word.strip_prefix("0x") {
+                Some(hex) => i64::from_str_radix(hex, 16),
+                None => word.parse(),
+            };
+            let number = number.map_err(|_| error(line, format!("Invalid number \"{}\"", word)))?;
+            tokens.push((line, Token::Number(number)));
+            len
+        } else if rest.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push((line, Token::Ident(rest[..len].to_string())));
+            len
+        } else {
+            let c = rest.chars().next().unwrap_or_default();
+            return Err(error(line, format!("Unexpected character \"{}\"", c)));
+        };
+
+        rest = rest[token_len..].trim_start();
+    }
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Min,
+    Max,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Number(i64),
+    /// A loop variable, by its slot.
+    Var(usize),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+enum EvalError {
+    NotConstant,
+    DivisionByZero,
+    Overflow,
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::NotConstant => write!(f, "Only constants may be used here, not loop variables"),
+            EvalError::DivisionByZero => write!(f, "Division by zero"),
+            EvalError::Overflow => write!(f, "Arithmetic overflow"),
+        }
+    }
+}
+
+impl Expr {
+    /// Evaluates the expression with the current values of the loop variables.
+    fn eval(&self, vars: &[i64]) -> Result<i64, EvalError> {
+        match self {
+            Expr::Number(number) => Ok(*number),
+            Expr::Var(slot) => vars.get(*slot).copied().ok_or(EvalError::NotConstant),
+            Expr::Binary(op, left, right) => {
+                let (left, right) = (left.eval(vars)?, right.eval(vars)?);
+                let result = match op {
+                    BinOp::Add => left.checked_add(right),
+                    BinOp::Sub => left.checked_sub(right),
+                    BinOp::Mul => left.checked_mul(right),
+                    BinOp::Div | BinOp::Rem if right == 0 => return Err(EvalError::DivisionByZero),
+                    BinOp::Div => left.checked_div(right),
+                    BinOp::Rem => left.checked_rem(right),
+                    BinOp::Min => Some(left.min(right)),
+                    BinOp::Max => Some(left.max(right)),
+                };
+                result.ok_or(EvalError::Overflow)
+            }
+        }
+    }
+}
+
+enum Stmt {
+    For {
+        line: usize,
+        var: usize,
+        start: Expr,
+        end: Expr,
+        step: Expr,
+        body: Rc<[Stmt]>,
+    },
+    Access {
+        line: usize,
+        kind: AccessKind,
+        array: usize,
+        indices: Vec<Expr>,
+    },
+}
+
+struct Array {
+    name: String,
+    dims: Vec<u64>,
+    element_size: u64,
+    base: u64,
+}
+
+/// A parsed kernel, which expands into its accesses.
+pub struct Kernel {
+    /// The cache of the `cache` line, if any.
+    pub cache: Option<CacheDesc>,
+    arrays: Rc<[Array]>,
+    body: Rc<[Stmt]>,
+    n_vars: usize,
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    consts: HashMap<String, i64>,
+    /// Loop variables in scope and their slots, innermost last.
+    scope: Vec<(String, usize)>,
+    n_vars: usize,
+    arrays: Vec<Array>,
+    next_base: u64,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        self.tokens.get(self.pos).map_or(&Token::End, |(_, token)| token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map_or(1, |(line, _)| *line)
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.peek().clone();
+        self.pos += 1;
+        token
+    }
+
+    fn expected(&self, what: &str) -> ParseError {
+        error(self.line(), format!("Expected {}, found {}", what, self.peek()))
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Token::Symbol(found) if *found == symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), ParseError> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(self.expected(&format!("\"{}\"", symbol)))
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Token::Ident(name) if name == keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_ident(&mut self, what: &str) -> Result<String, ParseError> {
+        match self.peek() {
+            Token::Ident(name) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => Err(self.expected(what)),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let line = self.line();
+        match self.next() {
+            Token::Number(number) => Ok(Expr::Number(number)),
+            Token::Symbol("(") => {
+                let expr = self.expr()?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            Token::Symbol("-") => Ok(Expr::Binary(
+                BinOp::Sub,
+                Box::new(Expr::Number(0)),
+                Box::new(self.primary()?),
+            )),
+            Token::Ident(name) if (name == "min" || name == "max") && *self.peek() == Token::Symbol("(") => {
+                self.pos += 1;
+                let left = self.expr()?;
+                self.expect_symbol(",")?;
+                let right = self.expr()?;
+                self.expect_symbol(")")?;
+                let op = if name == "min" { BinOp::Min } else { BinOp::Max };
+                Ok(Expr::Binary(op, Box::new(left), Box::new(right)))
+            }
+            Token::Ident(name) => {
+                if let Some((_, slot)) = self.scope.iter().rev().find(|(var, _)| *var == name) {
+                    Ok(Expr::Var(*slot))
+                } else if let Some(value) = self.consts.get(&name) {
+                    Ok(Expr::Number(*value))
+                } else {
+                    Err(error(line, format!("Unknown name \"{}\"", name)))
+                }
+            }
+            _ => {
+                self.pos -= 1;
+                Err(self.expected("a number, a name or \"(\""))
+            }
+        }
+    }
+
+    /// Parses operators of the given precedence level, 0 for + and -, 1 for * / and %.
+    fn binary(&mut self, level: u8) -> Result<Expr, ParseError> {
+        let operand = |parser: &mut Parser| if level == 0 { parser.binary(1) } else { parser.primary() };
+        let mut expr = operand(self)?;
+
+        loop {
+            let op = match (level, self.peek()) {
+                (0, Token::Symbol("+")) => BinOp::Add,
+                (0, Token::Symbol("-")) => BinOp::Sub,
+                (1, Token::Symbol("*")) => BinOp::Mul,
+                (1, Token::Symbol("/")) => BinOp::Div,
+                (1, Token::Symbol("%")) => BinOp::Rem,
+                _ => return Ok(expr),
+            };
+            self.pos += 1;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(operand(self)?));
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        self.binary(0)
+    }
+
+    /// Parses an expression that must not depend on loop variables.
+    fn constant(&mut self) -> Result<i64, ParseError> {
+        let line = self.line();
+        self.expr()?.eval(&[]).map_err(|err| error(line, err.to_string()))
+    }
+
+    fn unsigned(&mut self, what: &str) -> Result<u64, ParseError> {
+        let line = self.line();
+        u64::try_from(self.constant()?).map_err(|_| error(line, format!("{} must not be negative", what)))
+    }
+
+    /// Parses statements until the end of the file or the closing brace of a block.
+    fn block(&mut self) -> Result<Rc<[Stmt]>, ParseError> {
+        let mut stmts = Vec::new();
+        loop {
+            while self.eat_symbol(";") {}
+            if matches!(self.peek(), Token::End | Token::Symbol("}")) {
+                return Ok(stmts.into());
+            }
+            if let Some(stmt) = self.stmt()? {
+                stmts.push(stmt);
+            }
+        }
+    }
+
+    /// Parses a statement. Declarations return `None`, since they do not expand into accesses.
+    fn stmt(&mut self) -> Result<Option<Stmt>, ParseError> {
+        let line = self.line();
+        let keyword = self.expect_ident("a statement")?;
+
+        match keyword.as_str() {
+            "let" => {
+                let name = self.expect_ident("a name")?;
+                self.expect_symbol("=")?;
+                let value = self.constant()?;
+                self.consts.insert(name, value);
+                Ok(None)
+            }
+            "array" => {
+                let name = self.expect_ident("a name")?;
+                let mut dims = Vec::new();
+                while self.eat_symbol("[") {
+                    dims.push(self.unsigned("An array dimension")?);
+                    self.expect_symbol("]")?;
+                }
+
+                let mut element_size = 8;
+                let mut base = self.next_base;
+                loop {
+                    if self.eat_keyword("size") {
+                        element_size = self.unsigned("The element size")?;
+                    } else if self.eat_keyword("at") {
+                        base = self.unsigned("The base address")?;
+                    } else {
+                        break;
+                    }
+                }
+
+                let end = dims
+                    .iter()
+                    .try_fold(element_size, |size, dim| size.checked_mul(*dim))
+                    .and_then(|size| size.checked_add(base))
+                    .ok_or_else(|| error(line, format!("The array {} does not fit into 64-bit addresses", name)))?;
+                self.next_base = end;
+
+                self.arrays.push(Array {
+                    name,
+                    dims,
+                    element_size,
+                    base,
+                });
+                Ok(None)
+            }
+            "for" => {
+                let name = self.expect_ident("a loop variable")?;
+                if !self.eat_keyword("in") {
+                    return Err(self.expected("\"in\""));
+                }
+                let start = self.expr()?;
+                self.expect_symbol("..")?;
+                let end = self.expr()?;
+                let step = if self.eat_keyword("step") { self.expr()? } else { Expr::Number(1) };
+
+                self.expect_symbol("{")?;
+                let var = self.n_vars;
+                self.n_vars += 1;
+                self.scope.push((name, var));
+                let body = self.block()?;
+                self.scope.pop();
+                self.expect_symbol("}")?;
+
+                Ok(Some(Stmt::For {
+                    line,
+                    var,
+                    start,
+                    end,
+                    step,
+                    body,
+                }))
+            }
+            "read" | "write" | "modify" => {
+                let kind = match keyword.as_str() {
+                    "read" => AccessKind::Load,
+                    "write" => AccessKind::Store,
+                    _ => AccessKind::Modify,
+                };
+
+                let name = self.expect_ident("an array")?;
+                let array = self
+                    .arrays
+                    .iter()
+                    .position(|array| array.name == name)
+                    .ok_or_else(|| error(line, format!("Unknown array \"{}\"", name)))?;
+
+                let mut indices = Vec::new();
+                while self.eat_symbol("[") {
+                    indices.push(self.expr()?);
+                    self.expect_symbol("]")?;
+                }
+                let n_dims = self.arrays[array].dims.len();
+                if indices.len() != n_dims {
+                    return Err(error(
+                        line,
+                        format!("{} has {} dimensions, but {} indices are given", name, n_dims, indices.len()),
+                    ));
+                }
+
+                Ok(Some(Stmt::Access {
+                    line,
+                    kind,
+                    array,
+                    indices,
+                }))
+            }
+            _ => {
+                self.pos -= 1;
+                Err(self.expected("let, array, for, read, write or modify"))
+            }
+        }
+    }
+}
+
+pub fn parse(content: &str) -> Result<Kernel, ParseError> {
+    let mut cache = None;
+    let mut tokens = Vec::new();
+
+    for (i, text) in content.lines().enumerate() {
+        let line = i + 1;
+        match text.trim_start().strip_prefix("cache") {
+            Some(spec) if spec.starts_with(char::is_whitespace) => {
+                let spec = spec.split('#').next().unwrap_or("");
+                let desc = CacheDesc::from_spec(spec).map_err(|err| error(line, err.to_string()))?;
+                cache = Some(desc);
+            }
+            _ => tokenize(line, text, &mut tokens)?,
+        }
+    }
+
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        consts: HashMap::new(),
+        scope: Vec::new(),
+        n_vars: 0,
+        arrays: Vec::new(),
+        next_base: 0,
+    };
+    let body = parser.block()?;
+    if parser.peek() != &Token::End {
+        return Err(parser.expected("a statement"));
+    }
+
+    Ok(Kernel {
+        cache,
+        arrays: parser.arrays.into(),
+        body,
+        n_vars: parser.n_vars,
+    })
+}
+
+impl Kernel {
+    /// Returns the accesses of the kernel. They are expanded while iterating, so even large loop
+    /// nests take constant memory.
+    pub fn accesses(&self) -> Accesses {
+        Accesses {
+            arrays: self.arrays.clone(),
+            vars: vec![0; self.n_vars],
+            stack: vec![Frame {
+                body: self.body.clone(),
+                pc: 0,
+                repeat: None,
+            }],
+        }
+    }
+}
+
+/// A loop that is being executed.
+struct Repeat {
+    var: usize,
+    end: i64,
+    step: i64,
+}
+
+struct Frame {
+    body: Rc<[Stmt]>,
+    /// The next statement of `body`.
+    pc: usize,
+    /// The loop whose body this is, `None` for the kernel itself.
+    repeat: Option<Repeat>,
+}
+
+pub struct Accesses {
+    arrays: Rc<[Array]>,
+    vars: Vec<i64>,
+    stack: Vec<Frame>,
+}
+
+impl Accesses {
+    fn access(&self, line: usize, kind: AccessKind, array: usize, indices: &[Expr]) -> Result<Access, ParseError> {
+        let array = &self.arrays[array];
+
+        let mut offset = 0;
+        for (dim, index) in array.dims.iter().zip(indices) {
+            let value = index.eval(&self.vars).map_err(|err| error(line, err.to_string()))?;
+            let index = u64::try_from(value)
+                .ok()
+                .filter(|index| index < dim)
+                .ok_or_else(|| {
+                    error(line, format!("Index {} is out of bounds of {} with size {}", value, array.name, dim))
+                })?;
+            offset = offset * dim + index;
+        }
+
+        Ok(Access {
+            addr: array.base + offset * array.element_size,
+            size: array.element_size,
+            kind,
+        })
+    }
+
+    fn start_loop(&mut self, line: usize, var: usize, bounds: [&Expr; 3], body: &Rc<[Stmt]>) -> Result<(), ParseError> {
+        let [start, end, step] = bounds.map(|expr| expr.eval(&self.vars).map_err(|err| error(line, err.to_string())));
+        let (start, end, step) = (start?, end?, step?);
+        if step <= 0 {
+            return Err(error(line, format!("The step must be positive, found {}", step)));
+        }
+
+        if start < end {
+            self.vars[var] = start;
+            self.stack.push(Frame {
+                body: body.clone(),
+                pc: 0,
+                repeat: Some(Repeat { var, end, step }),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for Accesses {
+    type Item = Result<Access, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let frame = self.stack.last_mut()?;
+
+            if frame.pc == frame.body.len() {
+                if let Some(Repeat { var, end, step }) = frame.repeat {
+                    let next = self.vars[var].checked_add(step).filter(|next| *next < end);
+                    if let Some(next) = next {
+                        self.vars[var] = next;
+                        frame.pc = 0;
+                        continue;
+                    }
+                }
+                self.stack.pop();
+                continue;
+            }
+
+            let body = frame.body.clone();
+            let pc = frame.pc;
+            frame.pc += 1;
+
+            let result = match &body[pc] {
+                Stmt::Access { line, kind, array, indices } => self.access(*line, *kind, *array, indices).map(Some),
+                Stmt::For { line, var, start, end, step, body } => {
+                    self.start_loop(*line, *var, [start, end, step], body).map(|()| None)
+                }
+            };
+
+            match result {
+                Ok(Some(access)) => return Some(Ok(access)),
+                Ok(None) => {}
+                Err(err) => {
+                    // Nothing sensible follows an error.
+                    self.stack.clear();
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accesses(content: &str) -> Vec<(u64, u64, AccessKind)> {
+        parse(content)
+            .unwrap()
+            .accesses()
+            .map(|access| access.map(|access| (access.addr, access.size, access.kind)))
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    /// The line of the first error, whether it is found while parsing or while expanding.
+    fn error_line(content: &str) -> usize {
+        let error = match parse(content) {
+            Ok(kernel) => kernel.accesses().find_map(Result::err).expect("The kernel has no error"),
+            Err(error) => error,
+        };
+        match error {
+            ParseError::InvalidKernel { line, .. } => line,
+            error => panic!("unexpected error {}", error),
+        }
+    }
+
+    #[test]
+    fn nested_loops() {
+        let kernel = "
+            let N = 2
+            array A[N][3] size 4 at 0x100
+            array B[N]  # follows A
+            for i in 0..N {
+                for j in 0..3 step 2 { read A[i][j] }
+                write B[i]
+            }
+        ";
+        assert_eq!(
+            accesses(kernel),
+            [
+                (0x100, 4, AccessKind::Load),
+                (0x108, 4, AccessKind::Load),
+                (0x118, 8, AccessKind::Store),
+                (0x10c, 4, AccessKind::Load),
+                (0x114, 4, AccessKind::Load),
+                (0x120, 8, AccessKind::Store),
+            ]
+        );
+    }
+
+    #[test]
+    fn expressions() {
+        let kernel = "
+            cache 256B, 16B line, 2-way
+            array A[10] size 1
+            for t in 0..10 step 4 { for i in t..min(t + 4, 10) { modify A[(i * 3) % 10] } }
+        ";
+        let addrs: Vec<u64> = accesses(kernel).into_iter().map(|(addr, _, _)| addr).collect();
+        assert_eq!(addrs, [0, 3, 6, 9, 2, 5, 8, 1, 4, 7]);
+        let cache = parse(kernel).unwrap().cache.map(|cache| cache.spec());
+        assert_eq!(cache.as_deref(), Some("256B, 16B line, 2-way, LRU, 32-bit"));
+    }
+
+    #[test]
+    fn empty_loops() {
+        assert!(accesses("array A[4]\nfor i in 4..0 { read A[i] }").is_empty());
+    }
+
+    #[test]
+    fn error_lines() {
+        assert_eq!(error_line("array A[4]\n\nread B[0]"), 3);
+        // A missing brace is reported after the last statement.
+        assert_eq!(error_line("array A[4]\nfor i in 0..4 {\n    read A[i]\n\n# done"), 3);
+        assert_eq!(error_line("let N = 4\narray A[N]\nfor i in 0..N + 1 {\n    read A[i] $\n}"), 4);
+        assert_eq!(error_line("cache 3B line\narray A[1]"), 1);
+        // Found while expanding, in the line of the access or loop.
+        assert_eq!(error_line("array A[4]\nfor i in 0..5 {\n    read A[i]\n}"), 3);
+        assert_eq!(error_line("array A[4]\nfor i in 0..4 step 0 { read A[i] }"), 2);
+        assert_eq!(error_line("array A[4]\nfor i in 0..4 {\n    read A[i / (i - 2)]\n}"), 3);
+    }
+}
@@ -0,0 +1,95 @@
+// Memory traces of `valgrind --tool=lackey --trace-mem=yes`, e.g.
+//
+//     ==2137== Lackey, an example Valgrind tool
+//     I  04016d0,3
+//      S 7ff000398,8
+//      L 04221e28,8
+//      M 0421fe00,4
+//
+// Instruction fetches start in the first column, data accesses in the second.
+// Lines starting with "==" are messages of valgrind.
+
+use std::io::BufRead;
+
+use super::{column, Access, AccessKind, Format, Records};
+
+pub fn is_comment(text: &str) -> bool {
+    text.starts_with("==")
+}
+
+/// Parses a single record. Returns the column of the malformed field if it is malformed.
+pub fn parse_record(text: &str) -> Result<Access, usize> {
+    let record = text.trim_start();
+    let (kind, rest) = record.split_once(' ').unwrap_or((record, &record[record.len()..]));
+    let kind = match kind {
+        "I" => AccessKind::Instruction,
+        "L" => AccessKind::Load,
+        "S" => AccessKind::Store,
+        "M" => AccessKind::Modify,
+        _ => return Err(column(text, kind)),
+    };
+
+    let rest = rest.trim();
+    let (addr, size) = rest.split_once(',').unwrap_or((rest, &text[text.len()..]));
+
+    Ok(Access {
+        addr: u64::from_str_radix(addr, 16).map_err(|_| column(text, addr))?,
+        size: size.trim().parse().map_err(|_| column(text, size))?,
+        kind,
+    })
+}
+
+pub fn records<R: BufRead>(reader: R, strict: bool) -> Records<R> {
+    Records {
+        lines: reader.lines().enumerate(),
+        strict,
+        format: Format::Lackey,
+        skip: is_comment,
+        parse_record: |text| parse_record(text).map(Some),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sim::ParseError;
+
+    use super::*;
+
+    /// The line and column of the first malformed record of `trace`.
+    fn error(trace: &str) -> (usize, usize) {
+        match records(trace.as_bytes(), true).find_map(Result::err) {
+            Some(ParseError::InvalidRecord { line, column, format: "Lackey", .. }) => (line, column),
+            err => panic!("unexpected result {:?} for {:?}", err, trace),
+        }
+    }
+
+    #[test]
+    fn access_kinds() {
+        let trace = "==1== Lackey\nI  04016d0,3\n S 7ff000398,8\n\n L 04221e28,8\n M 0421fe00,4\n";
+        let accesses: Vec<_> = records(trace.as_bytes(), true).collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            accesses,
+            [
+                Access { addr: 0x4016d0, size: 3, kind: AccessKind::Instruction },
+                Access { addr: 0x7ff000398, size: 8, kind: AccessKind::Store },
+                Access { addr: 0x4221e28, size: 8, kind: AccessKind::Load },
+                Access { addr: 0x421fe00, size: 4, kind: AccessKind::Modify },
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_records() {
+        assert_eq!(error("I  04016d0,3\n X 1000,8"), (2, 2));
+        assert_eq!(error(" L 10zz,8"), (1, 4));
+        assert_eq!(error(" L 1000,eight"), (1, 9));
+        // Missing fields are reported after the end of the line.
+        assert_eq!(error(" L 1000"), (1, 8));
+        assert_eq!(error(" L"), (1, 3));
+        // Columns count characters, not bytes.
+        assert_eq!(error("ä L 1000,8"), (1, 1));
+
+        // Without `strict`, malformed records are skipped.
+        assert_eq!(records(" X 1000,8\n L 10,1".as_bytes(), false).count(), 1);
+    }
+}
@@ -0,0 +1,271 @@
+// Trace formats of other tools and the accesses they describe.
+// Unlike native traces, these do not describe the cache, so it has to come from a configuration.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, Read};
+use std::iter::{self, Enumerate};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::sim::{self, CacheDesc, ParseError};
+
+pub mod binary;
+mod compression;
+mod dinero;
+mod kernel;
+mod lackey;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessKind {
+    Load,
+    Store,
+    /// A load followed by a store to the same address.
+    Modify,
+    Instruction,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Access {
+    pub addr: u64,
+    /// Size in bytes. Accesses that cross a line boundary touch every line they overlap.
+    pub size: u64,
+    pub kind: AccessKind,
+}
+
+impl Access {
+    /// The addresses the simulator sees for this access, one per touched cache line with lines of
+    /// `offset_bits`. A modify touches its lines twice, once for loading and once for storing.
+    pub fn line_addrs(&self, offset_bits: u64) -> impl Iterator<Item = u64> {
+        let first = self.addr >> offset_bits;
+        let last = self.addr.saturating_add(self.size.max(1) - 1) >> offset_bits;
+        let repeat = if self.kind == AccessKind::Modify { 2 } else { 1 };
+
+        // The first line keeps the original address so its offset is not lost.
+        let lines = iter::once(self.addr).chain((first + 1..=last).map(move |line| line << offset_bits));
+        (0..repeat).flat_map(move |_| lines.clone())
+    }
+}
+
+/// The records of a trace, read one by one.
+pub type Accesses = Box<dyn Iterator<Item = Result<Access, ParseError>>>;
+
+/// A trace that is read record by record, so it never has to fit into memory.
+pub struct Trace {
+    /// The cache described by the trace, or why it does not describe one.
+    pub cache: Result<CacheDesc, ParseError>,
+    pub accesses: Accesses,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// The header and hexadecimal addresses read by `sim::read`.
+    Native,
+    /// Output of `valgrind --tool=lackey --trace-mem=yes`.
+    Lackey,
+    /// The din format of Dinero IV.
+    Dinero,
+    /// The compact format of `binary::Writer`.
+    Binary,
+    /// Loop nests that expand into accesses, see `kernel`.
+    Kernel,
+}
+
+impl Format {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Native => "native",
+            Format::Lackey => "Lackey",
+            Format::Dinero => "Dinero",
+            Format::Binary => "binary",
+            Format::Kernel => "kernel",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseFormatError;
+impl fmt::Display for ParseFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid trace format, expected native, lackey, dinero, binary or kernel")
+    }
+}
+
+impl std::error::Error for ParseFormatError {}
+
+impl FromStr for Format {
+    type Err = ParseFormatError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "native" => Ok(Format::Native),
+            "lackey" => Ok(Format::Lackey),
+            "dinero" | "din" => Ok(Format::Dinero),
+            "binary" | "bin" => Ok(Format::Binary),
+            "kernel" => Ok(Format::Kernel),
+            _ => Err(ParseFormatError),
+        }
+    }
+}
+
+/// Opens a trace file for reading, decompressing it if necessary. The path `-` means stdin.
+fn open_file(path: &Path) -> Result<Box<dyn BufRead>, ParseError> {
+    let reader: Box<dyn Read> = if path == Path::new("-") {
+        Box::new(io::stdin())
+    } else {
+        Box::new(File::open(path)?)
+    };
+    Ok(compression::decompress(reader)?)
+}
+
+/// Guesses the format of a trace from its magic bytes or its first record. Since stdin cannot be
+/// read twice, the returned reader yields everything again, including what was read to guess.
+fn detect(mut reader: Box<dyn BufRead>) -> io::Result<(Format, Box<dyn BufRead>)> {
+    let mut head = Vec::new();
+    reader.by_ref().take(binary::MAGIC.len() as u64).read_to_end(&mut head)?;
+
+    let format = if head.starts_with(binary::MAGIC) {
+        Format::Binary
+    } else {
+        let mut start = 0;
+        loop {
+            // The bytes read for the magic may already contain the next line.
+            if !head[start..].contains(&b'\n')
+                && reader.read_until(b'\n', &mut head)? == 0
+                && start == head.len()
+            {
+                break Format::Native;
+            }
+
+            let end = head[start..]
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .map_or(head.len(), |i| start + i + 1);
+            let line = String::from_utf8_lossy(&head[start..end]);
+            let line = line.trim_end_matches(['\n', '\r']);
+            start = end;
+            if lackey::is_comment(line) || line.trim().is_empty() {
+                continue;
+            }
+
+            break if kernel::is_kernel(line) {
+                Format::Kernel
+            } else if lackey::parse_record(line).is_ok() {
+                Format::Lackey
+            } else if dinero::parse_record(line).is_ok() {
+                Format::Dinero
+            } else {
+                Format::Native
+            };
+        }
+    };
+
+    Ok((format, Box::new(io::Cursor::new(head).chain(reader))))
+}
+
+/// Opens a trace of any format, guessing the format if it is not given. Only its header is read
+/// right away, the accesses are read while iterating, so traces can be piped in through stdin
+/// with the path `-`. Compressed traces are decompressed on the fly. Malformed records are
+/// skipped, unless `strict` is set.
+pub fn open(path: &Path, format: Option<Format>, strict: bool) -> Result<Trace, ParseError> {
+    let (format, mut reader) = match format {
+        Some(format) => (format, open_file(path)?),
+        None => detect(open_file(path)?)?,
+    };
+    let missing_cache = ParseError::MissingCache { format: format.name() };
+
+    Ok(match format {
+        Format::Native => {
+            let (cache, addrs) = sim::read(reader, strict)?;
+            let accesses = addrs.map(|addr| {
+                addr.map(|addr| Access {
+                    addr,
+                    size: 1,
+                    kind: AccessKind::Load,
+                })
+            });
+            Trace {
+                cache,
+                accesses: Box::new(accesses),
+            }
+        }
+        Format::Lackey => Trace {
+            cache: Err(missing_cache),
+            accesses: Box::new(lackey::records(reader, strict)),
+        },
+        Format::Dinero => Trace {
+            cache: Err(missing_cache),
+            accesses: Box::new(dinero::records(reader, strict)),
+        },
+        Format::Kernel => {
+            let mut content = String::new();
+            reader.read_to_string(&mut content)?;
+            let kernel = kernel::parse(&content)?;
+            Trace {
+                accesses: Box::new(kernel.accesses()),
+                cache: kernel.cache.ok_or(missing_cache),
+            }
+        }
+        Format::Binary => {
+            let (cache, accesses) = binary::read(reader)?;
+            Trace {
+                cache: cache.ok_or(missing_cache),
+                accesses: Box::new(accesses),
+            }
+        }
+    })
+}
+
+/// The column of `field`, which must be a part of `line`, counting characters from 1.
+fn column(line: &str, field: &str) -> usize {
+    let start = field.as_ptr() as usize - line.as_ptr() as usize;
+    line[..start].chars().count() + 1
+}
+
+/// Reads the records of the line based formats. `parse_record` returns `Ok(None)` for records
+/// that are valid but do not describe an access, and the column of the malformed field for
+/// malformed ones.
+struct Records<R> {
+    lines: Enumerate<io::Lines<R>>,
+    strict: bool,
+    format: Format,
+    skip: fn(&str) -> bool,
+    parse_record: fn(&str) -> Result<Option<Access>, usize>,
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = Result<Access, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (i, text) in self.lines.by_ref() {
+            let text = match text {
+                Ok(text) => text,
+                Err(err) => return Some(Err(err.into())),
+            };
+            if text.trim().is_empty() || (self.skip)(&text) {
+                continue;
+            }
+
+            match (self.parse_record)(&text) {
+                Ok(Some(access)) => return Some(Ok(access)),
+                Ok(None) => {}
+                Err(column) if self.strict => {
+                    return Some(Err(ParseError::InvalidRecord {
+                        line: i + 1,
+                        column,
+                        text,
+                        format: self.format.name(),
+                    }));
+                }
+                Err(_) => {}
+            }
+        }
+
+        None
+    }
+}
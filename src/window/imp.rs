@@ -1,16 +1,13 @@
-use std::path::PathBuf;
-
-use std::cell::RefCell;
-
 use gtk::prelude::*;
-use gtk::glib::{self, Properties};
+use gtk::glib::{self, clone};
 use gtk::subclass::prelude::*;
+use gtk::Orientation;
+use libadwaita::{TabBar, TabView};
 
-#[derive(Properties, Default)]
-#[properties(wrapper_type = super::CacheCacheWindow)]
+#[derive(Default)]
 pub struct CacheCacheWindow {
-    #[property(get, set)]
-    pub path_buf: RefCell<PathBuf>,
+    /// One page per open document.
+    pub tab_view: TabView,
 }
 
 #[glib::object_subclass]
@@ -21,25 +18,28 @@ impl ObjectSubclass for CacheCacheWindow {
 }
 
 impl ObjectImpl for CacheCacheWindow {
-    fn properties() -> &'static [glib::ParamSpec] {
-        Self::derived_properties()
-    }
-
-    fn set_property(&self, id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
-        self.derived_set_property(id, value, pspec)
-    }
-
-    fn property(&self, id: usize, pspec: &glib::ParamSpec) -> glib::Value {
-        self.derived_property(id, pspec)
-    }
-
     fn constructed(&self) {
         self.parent_constructed();
 
         let obj = self.obj();
-        let empty_path_buf = PathBuf::new();
-        dbg!(&empty_path_buf);
-        obj.set_path_buf(empty_path_buf);
+        // Only shown with more than one tab.
+        let tab_bar = TabBar::builder()
+            .view(&self.tab_view)
+            .autohide(true)
+            .build();
+        self.tab_view.set_vexpand(true);
+
+        let container_box = gtk::Box::new(Orientation::Vertical, 0);
+        container_box.append(&tab_bar);
+        container_box.append(&self.tab_view);
+        obj.set_child(Some(&container_box));
+
+        // Closing the last tab, or moving it to another window, closes the window.
+        self.tab_view.connect_n_pages_notify(clone!(@weak obj => move |tab_view| {
+            if tab_view.n_pages() == 0 {
+                obj.close();
+            }
+        }));
     }
 }
 
@@ -47,4 +47,4 @@ impl WidgetImpl for CacheCacheWindow {}
 
 impl WindowImpl for CacheCacheWindow {}
 
-impl ApplicationWindowImpl for CacheCacheWindow {} 
+impl ApplicationWindowImpl for CacheCacheWindow {}
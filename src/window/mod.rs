@@ -1,8 +1,15 @@
 mod imp;
+pub mod overrides;
+
+use std::path::PathBuf;
 
 use gtk::{glib::{self, object::ObjectBuilder}, gio};
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
 use glib::Object;
-use libadwaita::Application;
+use libadwaita::{Application, TabView};
+
+use crate::document::CacheCacheDocument;
 
 glib::wrapper! {
     pub struct CacheCacheWindow(ObjectSubclass<imp::CacheCacheWindow>)
@@ -20,7 +27,37 @@ impl CacheCacheWindow {
     pub fn builder() -> ObjectBuilder<'static, Self> {
         Object::builder()
             .property("default-height", 400)
-            .property("default-width", 500)
+            .property("default-width", 800)
             .property("title", "CacheCache")
     }
+
+    pub fn tab_view(&self) -> TabView {
+        self.imp().tab_view.clone()
+    }
+
+    /// The document of the selected tab, `None` if the window has no tabs.
+    pub fn document(&self) -> Option<CacheCacheDocument> {
+        self.imp().tab_view.selected_page().map(|page| page.child()).and_downcast()
+    }
+
+    /// Opens `document` in a new tab and selects it. The tab is titled after the trace.
+    pub fn add_document(&self, document: &CacheCacheDocument) {
+        let tab_view = &self.imp().tab_view;
+        let page = tab_view.append(document);
+        document.bind_property("path-buf", &page, "title")
+            .transform_to(|_, path_buf: PathBuf| {
+                let name = path_buf.file_name().map(|name| name.to_string_lossy().into_owned());
+                Some(name.unwrap_or_else(|| "No File Selected".to_string()).to_value())
+            })
+            .sync_create()
+            .build();
+        document.bind_property("path-buf", &page, "tooltip")
+            .transform_to(|_, path_buf: PathBuf| Some(path_buf.display().to_string().to_value()))
+            .sync_create()
+            .build();
+        document.bind_property("running", &page, "loading")
+            .sync_create()
+            .build();
+        tab_view.set_selected_page(&page);
+    }
 }
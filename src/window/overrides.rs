@@ -0,0 +1,180 @@
+// A button in the header bar to override fields of the simulated cache, like `--address-size`,
+// `--block-size`, `--block-count`, `--assoc` and `--strategy` on the command line. Empty fields
+// keep the value of the trace header or configuration. The fields show the overrides of the
+// selected tab.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+use gtk::glib::{self, clone, SourceId};
+use gtk::{prelude::*, Align, Button, DropDown, Entry, Grid, Label, MenuButton, Orientation, Popover};
+
+use crate::sim::{CacheOverrides, Strategy};
+
+/// How long typing has to pause before the entries are applied.
+const TYPING_DELAY: Duration = Duration::from_millis(500);
+
+/// The entries of the popover, one per field.
+struct Form {
+    addr_size: Entry,
+    block_size: Entry,
+    n_blocks: Entry,
+    assoc: Entry,
+    /// "From trace" and then `Strategy::ALL`.
+    strategy: DropDown,
+    /// Set while the fields are filled in from a tab, which is no change to report.
+    filling: Cell<bool>,
+}
+
+/// The number in `entry`, `None` if it is empty. Invalid numbers mark the entry as an error.
+fn parse_entry(entry: &Entry) -> Result<Option<u64>, ()> {
+    let result = match entry.text().trim() {
+        "" => Ok(None),
+        text => text.parse().map(Some).map_err(|_| ()),
+    };
+    if result.is_ok() {
+        entry.remove_css_class("error");
+    } else {
+        entry.add_css_class("error");
+    }
+    result
+}
+
+impl Form {
+    /// The overrides of all fields, `None` as long as one of them is not a number.
+    fn overrides(&self) -> Option<CacheOverrides> {
+        let fields = [&self.addr_size, &self.block_size, &self.n_blocks, &self.assoc].map(parse_entry);
+        let [Ok(addr_size), Ok(block_size), Ok(n_blocks), Ok(assoc)] = fields else {
+            return None;
+        };
+        let strategy = (self.strategy.selected() as usize).checked_sub(1).map(|index| Strategy::ALL[index]);
+
+        Some(CacheOverrides { cache: None, addr_size, block_size, n_blocks, assoc, strategy })
+    }
+
+    fn set(&self, overrides: &CacheOverrides) {
+        let text = |value: Option<u64>| value.map_or(String::new(), |value| value.to_string());
+        self.addr_size.set_text(&text(overrides.addr_size));
+        self.block_size.set_text(&text(overrides.block_size));
+        self.n_blocks.set_text(&text(overrides.n_blocks));
+        self.assoc.set_text(&text(overrides.assoc));
+        let strategy = overrides.strategy.and_then(|strategy| Strategy::ALL.iter().position(|&other| other == strategy));
+        self.strategy.set_selected(strategy.map_or(0, |index| index as u32 + 1));
+    }
+}
+
+/// Builds the button. The fields are filled in with `current` whenever the popover opens, and
+/// `changed` is called with the new overrides whenever a field changes and all of them are valid.
+pub fn button(
+    current: impl Fn() -> Option<CacheOverrides> + 'static,
+    changed: impl Fn(CacheOverrides) + 'static,
+) -> MenuButton {
+    let entry = |tooltip: &str| {
+        Entry::builder()
+            .placeholder_text("From trace")
+            .tooltip_text(tooltip)
+            .width_chars(10)
+            .build()
+    };
+
+    let mut strategies = vec!["From trace".to_string()];
+    strategies.extend(Strategy::ALL.iter().map(Strategy::to_string));
+    let strategies: Vec<&str> = strategies.iter().map(String::as_str).collect();
+    let form = Rc::new(Form {
+        addr_size: entry("Address size in bits"),
+        block_size: entry("Block size in bits, i.e. the number of offset bits"),
+        n_blocks: entry("Number of blocks"),
+        assoc: entry("Associativity, 1 for a direct-mapped cache"),
+        strategy: DropDown::from_strings(&strategies),
+        filling: Cell::new(false),
+    });
+
+    let grid = Grid::builder()
+        .row_spacing(10)
+        .column_spacing(10)
+        .build();
+    let rows: [(&str, &gtk::Widget); 5] = [
+        ("Address Size", form.addr_size.upcast_ref()),
+        ("Block Size", form.block_size.upcast_ref()),
+        ("Block Count", form.n_blocks.upcast_ref()),
+        ("Associativity", form.assoc.upcast_ref()),
+        ("Strategy", form.strategy.upcast_ref()),
+    ];
+    for (row, (title, widget)) in rows.into_iter().enumerate() {
+        let label = Label::builder().label(title).halign(Align::Start).build();
+        grid.attach(&label, 0, row as i32, 1, 1);
+        grid.attach(widget, 1, row as i32, 1, 1);
+    }
+
+    let reset_button = Button::builder()
+        .label("Use Trace Values")
+        .build();
+
+    let container_box = gtk::Box::new(Orientation::Vertical, 10);
+    container_box.append(&grid);
+    container_box.append(&reset_button);
+
+    let popover = Popover::builder().child(&container_box).build();
+    let menu_button = MenuButton::builder()
+        .icon_name("preferences-system-symbolic")
+        .tooltip_text("Override Cache Parameters")
+        .popover(&popover)
+        .build();
+
+    popover.connect_show(clone!(@strong form => move |_| {
+        if let Some(overrides) = current() {
+            form.filling.set(true);
+            form.set(&overrides);
+            form.filling.set(false);
+        }
+    }));
+
+    // Typing is applied once it pauses, so "128" is not simulated as "1" and "12" first.
+    let pending: Rc<RefCell<Option<SourceId>>> = Rc::default();
+    let on_change = Rc::new(clone!(@strong form, @strong pending => move || {
+        if let Some(source) = pending.take() {
+            source.remove();
+        }
+        if form.filling.get() {
+            return;
+        }
+        if let Some(overrides) = form.overrides() {
+            changed(overrides);
+        }
+    }));
+    for entry in [&form.addr_size, &form.block_size, &form.n_blocks, &form.assoc] {
+        entry.connect_changed(clone!(@strong form, @strong pending, @strong on_change => move |_| {
+            if form.filling.get() {
+                return;
+            }
+            if let Some(source) = pending.take() {
+                source.remove();
+            }
+            let source = glib::timeout_add_local_once(TYPING_DELAY, clone!(@strong pending, @strong on_change => move || {
+                // The source is done, so it must not be removed again.
+                pending.take();
+                on_change();
+            }));
+            pending.replace(Some(source));
+        }));
+        entry.connect_activate(clone!(@strong on_change => move |_| on_change()));
+    }
+    form.strategy.connect_selected_notify(clone!(@strong on_change => move |_| on_change()));
+
+    // Edits that are still pending belong to the tab that was selected while they were typed.
+    popover.connect_closed(clone!(@strong pending, @strong on_change => move |_| {
+        if pending.borrow().is_some() {
+            on_change();
+        }
+    }));
+
+    reset_button.connect_clicked(clone!(@strong form, @strong on_change => move |_| {
+        form.filling.set(true);
+        form.set(&CacheOverrides::default());
+        form.filling.set(false);
+        on_change();
+    }));
+
+    menu_button
+}